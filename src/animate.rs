@@ -0,0 +1,428 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    image::{encode_ppm, Rgb},
+    Tilemap,
+};
+
+/// Where the frames are going. Opened when the first frame shows up, since
+/// that's when we find out how big they are.
+enum Sink {
+    Unopened,
+    Gif(BufWriter<File>),
+    /// A directory of `frame_00000.ppm`, `frame_00001.ppm`, ...
+    Frames(PathBuf),
+}
+
+/// Records a simulation one `Tilemap` at a time. Tiles are turned into
+/// palette indices by a closure, and the frames go out as an animated GIF if
+/// the path ends in `.gif` (and the palette and frame size fit), or as a
+/// directory of numbered PPMs otherwise.
+pub struct Recorder {
+    path: PathBuf,
+    palette: Vec<Rgb>,
+    scale: i32,
+    every: usize,
+    max_frames: usize,
+    delay: u16,
+    /// How many times `capture` has been called, skipped or not.
+    steps: usize,
+    frames: usize,
+    size: Option<(i32, i32)>,
+    sink: Sink,
+}
+
+impl Recorder {
+    pub fn new(path: impl AsRef<Path>, palette: &[Rgb]) -> Recorder {
+        assert!(!palette.is_empty(), "a palette needs at least one color");
+        Recorder {
+            path: path.as_ref().to_path_buf(),
+            palette: palette.to_vec(),
+            scale: 1,
+            every: 1,
+            max_frames: usize::MAX,
+            delay: 5,
+            steps: 0,
+            frames: 0,
+            size: None,
+            sink: Sink::Unopened,
+        }
+    }
+    /// Makes each tile a `scale`×`scale` square.
+    pub fn scale(mut self, scale: i32) -> Self {
+        assert!(scale > 0, "scale must be positive, not {scale}");
+        self.scale = scale;
+        self
+    }
+    /// Only keeps every `every`th step (always including the first).
+    pub fn every(mut self, every: usize) -> Self {
+        self.every = every.max(1);
+        self
+    }
+    /// Stops recording after this many frames.
+    pub fn max_frames(mut self, max_frames: usize) -> Self {
+        self.max_frames = max_frames;
+        self
+    }
+    /// How long each frame stays up, in hundredths of a second. (Only GIFs
+    /// care.)
+    pub fn delay(mut self, delay: u16) -> Self {
+        self.delay = delay;
+        self
+    }
+    /// How many frames have actually been written.
+    pub fn frame_count(&self) -> usize {
+        self.frames
+    }
+    /// Takes a snapshot of `map`, unless this step is being skipped. Every
+    /// frame has to be the same size as the first one.
+    pub fn capture<T: Clone>(
+        &mut self,
+        map: &Tilemap<T>,
+        f: impl Fn(&T) -> u8,
+    ) -> std::io::Result<()> {
+        let step = self.steps;
+        self.steps += 1;
+        if !step.is_multiple_of(self.every) {
+            return Ok(());
+        }
+        self.capture_now(map, f)
+    }
+    /// Like `capture`, but never skipped (except for `max_frames`). Good for
+    /// the final state, which you'd hate to lose to frame skipping.
+    pub fn capture_now<T: Clone>(
+        &mut self,
+        map: &Tilemap<T>,
+        f: impl Fn(&T) -> u8,
+    ) -> std::io::Result<()> {
+        if self.frames >= self.max_frames {
+            return Ok(());
+        }
+        let width = map.get_width() * self.scale;
+        let height = map.get_height() * self.scale;
+        match self.size {
+            None => self.size = Some((width, height)),
+            Some(size) => assert_eq!(
+                size,
+                (width, height),
+                "every frame has to be the same size"
+            ),
+        }
+        if let Sink::Unopened = self.sink {
+            self.sink = self.open(width, height)?;
+        }
+        let indices = map.to_pixels(|x| [f(x), 0, 0], self.scale);
+        let indices: Vec<u8> = indices.iter().map(|x| x[0]).collect();
+        match &mut self.sink {
+            Sink::Gif(out) => {
+                write_gif_frame(out, width, height, self.delay, &indices)?
+            }
+            Sink::Frames(dir) => {
+                let mut pixels = Tilemap::new_empty();
+                for row in indices.chunks_exact(width as usize) {
+                    let row: Vec<Rgb> = row
+                        .iter()
+                        .map(|x| self.palette[*x as usize])
+                        .collect();
+                    pixels.add_row(&row);
+                }
+                let path = dir.join(format!("frame_{:05}.ppm", self.frames));
+                std::fs::write(path, encode_ppm(&pixels))?;
+            }
+            Sink::Unopened => unreachable!(),
+        }
+        self.frames += 1;
+        Ok(())
+    }
+    fn open(&self, width: i32, height: i32) -> std::io::Result<Sink> {
+        let is_gif = self
+            .path
+            .extension()
+            .is_some_and(|x| x.eq_ignore_ascii_case("gif"));
+        let fits = self.palette.len() <= 256
+            && u16::try_from(width).is_ok()
+            && u16::try_from(height).is_ok();
+        if is_gif && fits {
+            let mut out = BufWriter::new(File::create(&self.path)?);
+            write_gif_header(&mut out, width, height, &self.palette)?;
+            Ok(Sink::Gif(out))
+        } else {
+            let dir = if is_gif {
+                // Too many colors (or pixels) for a GIF, so fall back to
+                // frames next to where the GIF would have gone.
+                self.path.with_extension("frames")
+            } else {
+                self.path.clone()
+            };
+            eprintln!("Writing frames to {}", dir.display());
+            std::fs::create_dir_all(&dir)?;
+            Ok(Sink::Frames(dir))
+        }
+    }
+    /// Finishes the file off. Dropping a `Recorder` without calling this
+    /// leaves a GIF without its trailer, which most viewers put up with, but
+    /// not all.
+    pub fn finish(self) -> std::io::Result<()> {
+        if let Sink::Gif(mut out) = self.sink {
+            out.write_all(&[0x3B])?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// A width or height the way GIF writes it, as long as it fits.
+fn gif_dimension(size: i32) -> std::io::Result<[u8; 2]> {
+    match u16::try_from(size) {
+        Ok(size) => Ok(size.to_le_bytes()),
+        Err(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("{size} pixels is too big for a GIF"),
+        )),
+    }
+}
+
+/// How many bits it takes to index the palette (at least 2, because GIF
+/// says so).
+fn palette_bits(len: usize) -> u8 {
+    let mut bits = 2;
+    while (1 << bits) < len {
+        bits += 1;
+    }
+    bits
+}
+
+fn write_gif_header(
+    out: &mut impl Write,
+    width: i32,
+    height: i32,
+    palette: &[Rgb],
+) -> std::io::Result<()> {
+    let bits = palette_bits(palette.len());
+    out.write_all(b"GIF89a")?;
+    out.write_all(&gif_dimension(width)?)?;
+    out.write_all(&gif_dimension(height)?)?;
+    // Global color table, 8 bits per primary, 2^bits colors
+    out.write_all(&[0xF0 | (bits - 1), 0, 0])?;
+    for index in 0..1 << bits {
+        out.write_all(palette.get(index).unwrap_or(&[0; 3]))?;
+    }
+    // Loop forever
+    out.write_all(b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\0\0\0")
+}
+
+fn write_gif_frame(
+    out: &mut impl Write,
+    width: i32,
+    height: i32,
+    delay: u16,
+    indices: &[u8],
+) -> std::io::Result<()> {
+    out.write_all(&[0x21, 0xF9, 4, 0])?;
+    out.write_all(&delay.to_le_bytes())?;
+    out.write_all(&[0, 0])?;
+    out.write_all(&[0x2C, 0, 0, 0, 0])?;
+    out.write_all(&gif_dimension(width)?)?;
+    out.write_all(&gif_dimension(height)?)?;
+    out.write_all(&[0])?;
+    let max_index = indices.iter().copied().max().unwrap_or(0);
+    let min_code_size = palette_bits(max_index as usize + 1);
+    out.write_all(&[min_code_size])?;
+    let data = lzw_encode(indices, min_code_size);
+    for block in data.chunks(255) {
+        out.write_all(&[block.len() as u8])?;
+        out.write_all(block)?;
+    }
+    out.write_all(&[0])
+}
+
+/// GIF-flavored LZW: variable-width codes packed least significant bit
+/// first, starting with a clear code and ending with end-of-information.
+/// The tricky part is knowing when to widen the codes, which has to happen
+/// exactly when the decoder does it, and the decoder's table is always one
+/// entry behind ours. So this keeps track of the decoder's table size too.
+fn lzw_encode(indices: &[u8], min_code_size: u8) -> Vec<u8> {
+    const MAX_CODE: u16 = 4096;
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut out = vec![];
+    let mut bit_buffer = 0u32;
+    let mut bit_count = 0;
+    let mut emit = |code: u16, width: u8, out: &mut Vec<u8>| {
+        bit_buffer |= (code as u32) << bit_count;
+        bit_count += width;
+        while bit_count >= 8 {
+            out.push(bit_buffer as u8);
+            bit_buffer >>= 8;
+            bit_count -= 8;
+        }
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = clear + 2;
+    // What the decoder will think, once it's read what we've written.
+    let mut width = min_code_size + 1;
+    let mut decoder_next = clear + 2;
+    let mut decoder_fresh = true;
+    let mut write_code = |code: u16, out: &mut Vec<u8>| {
+        emit(code, width, out);
+        if code == clear {
+            width = min_code_size + 1;
+            decoder_next = clear + 2;
+            decoder_fresh = true;
+        } else if code != end {
+            if !decoder_fresh && decoder_next < MAX_CODE {
+                decoder_next += 1;
+                if decoder_next == 1 << width && width < 12 {
+                    width += 1;
+                }
+            }
+            decoder_fresh = false;
+        }
+    };
+    write_code(clear, &mut out);
+    let mut pixels = indices.iter().copied();
+    if let Some(first) = pixels.next() {
+        let mut prefix = first as u16;
+        for pixel in pixels {
+            if let Some(&code) = table.get(&(prefix, pixel)) {
+                prefix = code;
+                continue;
+            }
+            write_code(prefix, &mut out);
+            if next < MAX_CODE {
+                table.insert((prefix, pixel), next);
+                next += 1;
+            } else {
+                write_code(clear, &mut out);
+                table.clear();
+                next = clear + 2;
+            }
+            prefix = pixel as u16;
+        }
+        write_code(prefix, &mut out);
+    }
+    write_code(end, &mut out);
+    // Flush whatever's left over.
+    emit(0, 7, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A plain GIF LZW decoder, to check the encoder against.
+    fn lzw_decode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut width = min_code_size + 1;
+        let mut prev: Option<Vec<u8>> = None;
+        let mut out = vec![];
+        let mut bits = data
+            .iter()
+            .flat_map(|byte| (0..8).map(move |bit| (byte >> bit) & 1));
+        loop {
+            let mut code = 0;
+            for bit in 0..width {
+                code |= (bits.next().unwrap() as usize) << bit;
+            }
+            if code == clear {
+                table = (0..clear).map(|x| vec![x as u8]).collect();
+                table.push(vec![]);
+                table.push(vec![]);
+                width = min_code_size + 1;
+                prev = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return out;
+            }
+            let entry = match (&prev, table.get(code)) {
+                (_, Some(entry)) => entry.clone(),
+                (Some(prev), None) => {
+                    let mut entry = prev.clone();
+                    entry.push(prev[0]);
+                    entry
+                }
+                (None, None) => panic!("bad code {code}"),
+            };
+            out.extend_from_slice(&entry);
+            if let Some(mut prev) = prev.take() {
+                if table.len() < 4096 {
+                    prev.push(entry[0]);
+                    table.push(prev);
+                    if table.len() == 1 << width && width < 12 {
+                        width += 1;
+                    }
+                }
+            }
+            prev = Some(entry);
+        }
+    }
+
+    #[test]
+    fn lzw_round_trip() {
+        let mut state = 12345u32;
+        for (len, colors) in [(0, 4), (1, 2), (100, 3), (50_000, 17)] {
+            let indices: Vec<u8> = (0..len)
+                .map(|x| {
+                    state = state.wrapping_mul(1103515245).wrapping_add(12345);
+                    // Long runs and noise, so the table both grows and fills.
+                    if x % 1000 < 500 {
+                        (x / 1000 % colors) as u8
+                    } else {
+                        ((state >> 16) % colors as u32) as u8
+                    }
+                })
+                .collect();
+            let bits = palette_bits(colors);
+            let encoded = lzw_encode(&indices, bits);
+            assert_eq!(lzw_decode(&encoded, bits), indices);
+        }
+    }
+
+    #[test]
+    fn frame_skipping() {
+        let dir = std::env::temp_dir()
+            .join(format!("advent_2023_frames_{}", std::process::id()));
+        let mut recorder = Recorder::new(&dir, &[[0; 3], [255; 3]])
+            .every(3)
+            .max_frames(2);
+        let map = Tilemap::new_with(true, 2, 2);
+        for _ in 0..10 {
+            recorder.capture(&map, |x| *x as u8).unwrap();
+        }
+        assert_eq!(recorder.frame_count(), 2);
+        recorder.finish().unwrap();
+        let mut names: Vec<_> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|x| x.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["frame_00000.ppm", "frame_00001.ppm"]);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn too_big_for_gif() {
+        let mut out = vec![];
+        assert!(write_gif_header(&mut out, 70_000, 1, &[[0; 3]]).is_err());
+        assert!(write_gif_frame(&mut out, 1, -1, 5, &[0]).is_err());
+        // The recorder sees it coming and writes frames instead.
+        let path = std::env::temp_dir()
+            .join(format!("advent_2023_wide_{}.gif", std::process::id()));
+        let mut recorder = Recorder::new(&path, &[[0; 3], [255; 3]]);
+        let map = Tilemap::new_with(true, 70_000, 1);
+        recorder.capture(&map, |x| *x as u8).unwrap();
+        recorder.finish().unwrap();
+        assert!(!path.exists());
+        let dir = path.with_extension("frames");
+        assert!(dir.join("frame_00000.ppm").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
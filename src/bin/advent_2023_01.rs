@@ -5,9 +5,9 @@ fn main() {
             .lines()
             .map(|x| {
                 let x = x.unwrap();
-                let digits = x.chars().filter(|x| x.is_ascii_digit());
+                let mut digits = x.chars().filter(|x| x.is_ascii_digit());
                 let first = digits.clone().next().unwrap();
-                let last = digits.last().unwrap();
+                let last = digits.next_back().unwrap();
                 [first, last]
                     .into_iter()
                     .collect::<String>()
@@ -1,52 +1,158 @@
+use std::ops::RangeInclusive;
+
+use anyhow::{anyhow, bail};
+use num::BigUint;
+
 #[derive(Debug)]
 struct Race {
     /// Number of milliseconds the race will last
-    time: u64,
+    time: u128,
     /// Number of millimeters the recordholder went
-    distance: u64,
+    distance: u128,
 }
 
 impl Race {
-    fn get_num_solutions(&self) -> u64 {
-        (0..self.time)
-            .map(|charge_time| (self.time - charge_time) * charge_time)
-            .filter(|distance| *distance > self.distance)
-            .count() as u64
+    /// Whether holding the button for `charge_time` beats the record. Too
+    /// big to multiply out means way past it.
+    fn wins(&self, charge_time: u128) -> bool {
+        charge_time
+            .checked_mul(self.time - charge_time)
+            .is_none_or(|x| x > self.distance)
+    }
+    /// Every charge time that beats the record. Going `c` millimeters means
+    /// `c * (time - c) > distance`, so the answer lies strictly between the
+    /// roots of `c² - time*c + distance`, which are `(time ± √(time² -
+    /// 4*distance)) / 2`. The square root is rounded down, and then the
+    /// edges get nudged over by checking them exactly, so ties (which don't
+    /// win) land on the right side.
+    fn get_winning_times(&self) -> Option<RangeInclusive<u128>> {
+        let time = BigUint::from(self.time);
+        let four_distance = BigUint::from(self.distance) * 4u32;
+        let squared = &time * &time;
+        if squared <= four_distance {
+            return None;
+        }
+        let root: u128 = (squared - four_distance).sqrt().try_into().unwrap();
+        let mut low = (self.time - root) / 2;
+        while low > 0 && self.wins(low - 1) {
+            low -= 1;
+        }
+        while low <= self.time / 2 && !self.wins(low) {
+            low += 1;
+        }
+        if low > self.time / 2 {
+            return None;
+        }
+        // The distance is symmetric about time / 2.
+        Some(low..=self.time - low)
+    }
+    fn get_num_solutions(&self) -> u128 {
+        self.get_winning_times()
+            .map_or(0, |x| x.end() - x.start() + 1)
     }
 }
 
-fn main() {
-    let mut lines = std::io::stdin().lines().map(|x| x.unwrap());
-    let line = lines.next().unwrap();
-    let (_, rest) = line.split_once(':').unwrap();
-    let times = rest
-        .split(' ')
-        .filter(|x| !x.is_empty())
-        .map(|x| x.parse().unwrap());
-    let line = lines.next().unwrap();
-    let (_, rest) = line.split_once(':').unwrap();
-    let distances = rest
-        .split(' ')
-        .filter(|x| !x.is_empty())
-        .map(|x| x.parse().unwrap());
-    let races: Vec<Race> = times
+/// Reads a line like `Time:      7  15   30`. With `kerning`, the spaces
+/// don't count, so that's one race that's `71530` milliseconds long.
+fn parse_numbers(
+    line: &str,
+    label: &str,
+    kerning: bool,
+) -> anyhow::Result<Vec<u128>> {
+    let rest = line
+        .strip_prefix(label)
+        .and_then(|x| x.strip_prefix(':'))
+        .ok_or(anyhow!("expected {label:?} line, got {line:?}"))?;
+    if kerning {
+        let digits: String =
+            rest.chars().filter(|x| !x.is_whitespace()).collect();
+        if digits.is_empty() {
+            bail!("no numbers on the {label:?} line");
+        }
+        Ok(vec![digits.parse()?])
+    } else {
+        Ok(rest
+            .split_whitespace()
+            .map(|x| x.parse())
+            .collect::<Result<_, _>>()?)
+    }
+}
+
+fn parse_races(
+    time_line: &str,
+    distance_line: &str,
+    kerning: bool,
+) -> anyhow::Result<Vec<Race>> {
+    let times = parse_numbers(time_line, "Time", kerning)?;
+    let distances = parse_numbers(distance_line, "Distance", kerning)?;
+    if times.len() != distances.len() {
+        bail!("{} times but {} distances", times.len(), distances.len());
+    }
+    Ok(times
+        .into_iter()
         .zip(distances)
         .map(|(time, distance)| Race { time, distance })
-        .collect();
+        .collect())
+}
+
+fn main() {
+    let mut lines = std::io::stdin().lines().map(|x| x.unwrap());
+    let time_line = lines.next().unwrap();
+    let distance_line = lines.next().unwrap();
+    let races = parse_races(&time_line, &distance_line, false).unwrap();
+    for (index, race) in races.iter().enumerate() {
+        match race.get_winning_times() {
+            Some(times) => println!(
+                "Race {}: hold for {times:?} ms, {} ways",
+                index + 1,
+                race.get_num_solutions()
+            ),
+            None => println!("Race {}: can't win", index + 1),
+        }
+    }
     println!(
         "Part 1 solution: {}",
-        races.iter().map(Race::get_num_solutions).product::<u64>(),
-    );
-    let superrace = races.iter().fold(
-        Race {
-            time: 0,
-            distance: 0,
-        },
-        |total, race| Race {
-            time: total.time * 10u64.pow(race.time.ilog10() + 1) + race.time,
-            distance: total.distance * 10u64.pow(race.distance.ilog10() + 1)
-                + race.distance,
-        },
+        races.iter().map(Race::get_num_solutions).product::<u128>(),
     );
+    let superrace = parse_races(&time_line, &distance_line, true)
+        .unwrap()
+        .pop()
+        .unwrap();
+    if let Some(times) = superrace.get_winning_times() {
+        println!("Superrace: hold for {times:?} ms");
+    }
     println!("Part 2 solution: {}", superrace.get_num_solutions());
 }
+
+#[test]
+fn test_sample_races() {
+    let sample = include_str!("../../sample/06.txt");
+    let (time_line, distance_line) = sample.split_once('\n').unwrap();
+    let races = parse_races(time_line, distance_line.trim(), false).unwrap();
+    assert_eq!(
+        races
+            .iter()
+            .map(Race::get_num_solutions)
+            .collect::<Vec<_>>(),
+        [4, 8, 9]
+    );
+    // 10 and 20 ms exactly tie the record, which isn't good enough.
+    assert_eq!(races[2].get_winning_times(), Some(11..=19));
+    let superrace = parse_races(time_line, distance_line.trim(), true)
+        .unwrap()
+        .pop()
+        .unwrap();
+    assert_eq!(superrace.get_num_solutions(), 71503);
+    // Exactly 4*distance = time², so the only candidate ties.
+    let tie = Race {
+        time: 10,
+        distance: 25,
+    };
+    assert_eq!(tie.get_winning_times(), None);
+    // Too big to square in a u128.
+    let huge = Race {
+        time: u128::MAX,
+        distance: 0,
+    };
+    assert_eq!(huge.get_winning_times(), Some(1..=u128::MAX - 1));
+}
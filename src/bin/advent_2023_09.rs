@@ -1,44 +1,130 @@
-fn predict(sequence: &[i32]) -> i32 {
-    let delta: Vec<i32> = sequence
-        .windows(2)
-        .map(|x| {
-            let &[prev, next] = x else { panic!() };
-            next - prev
+use anyhow::bail;
+use num::{BigInt, Zero};
+
+/// The lowest degree polynomial that goes through every value in a history,
+/// where the first value is at index 0. Newton's forward differences give
+/// the polynomial as `Σ Δᵏ(0) * C(n, k)`, so all it takes is the first
+/// number of each row of differences.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Polynomial {
+    /// The first number of each row of differences, down to (but not
+    /// including) the row of zeros.
+    leading_differences: Vec<BigInt>,
+}
+
+impl Polynomial {
+    /// Takes differences until they're all zero. If they never are, there
+    /// isn't enough history to say what comes next, so that's an error.
+    fn fit(history: &[i64]) -> anyhow::Result<Polynomial> {
+        let mut row: Vec<BigInt> =
+            history.iter().map(|x| (*x).into()).collect();
+        let mut leading_differences = vec![];
+        while !row.iter().all(|x| x.is_zero()) {
+            leading_differences.push(row[0].clone());
+            row = row
+                .windows(2)
+                .map(|x| {
+                    let [prev, next] = x else { unreachable!() };
+                    next - prev
+                })
+                .collect();
+        }
+        if row.is_empty() {
+            bail!("{history:?} never gets down to a row of zeros");
+        }
+        Ok(Polynomial {
+            leading_differences,
         })
-        .collect();
-    if delta.iter().all(|x| *x == 0) {
-        0
-    } else {
-        delta.last().unwrap() + predict(&delta)
+    }
+    /// A history that's zero all the way through counts as degree 0.
+    fn degree(&self) -> usize {
+        self.leading_differences.len().saturating_sub(1)
+    }
+    /// The value at any index at all, even way off in either direction.
+    fn at(&self, index: i64) -> BigInt {
+        let n = BigInt::from(index);
+        let mut ret = BigInt::zero();
+        // C(n, k), which works for negative n too, and stays a whole number
+        // after each step because a product of k + 1 numbers in a row is
+        // divisible by (k + 1)!.
+        let mut choose = BigInt::from(1);
+        for (k, difference) in self.leading_differences.iter().enumerate() {
+            ret += difference * &choose;
+            choose = choose * (&n - k) / (k + 1);
+        }
+        ret
     }
 }
 
 fn main() {
+    let mut queries = vec![];
+    let mut show_degrees = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--at" => queries.push(
+                args.next()
+                    .expect("--at needs an index")
+                    .parse::<i64>()
+                    .expect("--at needs an index"),
+            ),
+            "--degrees" => show_degrees = true,
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
-    let values: Vec<Vec<i32>> = lines
+    let values: Vec<Vec<i64>> = lines
         .map(|x| x.split(' ').map(|x| x.parse().unwrap()).collect())
         .collect();
+    let polynomials: Vec<Polynomial> =
+        values.iter().map(|x| Polynomial::fit(x).unwrap()).collect();
+    if show_degrees {
+        for (index, polynomial) in polynomials.iter().enumerate() {
+            println!("History {}: degree {}", index + 1, polynomial.degree());
+        }
+    }
+    for index in queries {
+        println!(
+            "Sum at {index}: {}",
+            polynomials.iter().map(|x| x.at(index)).sum::<BigInt>()
+        );
+    }
     println!(
         "Part 1 answer: {}",
         values
             .iter()
-            .map(|x| x.last().unwrap() + predict(x))
-            .sum::<i32>(),
+            .zip(polynomials.iter())
+            .map(|(history, polynomial)| polynomial.at(history.len() as i64))
+            .sum::<BigInt>(),
     );
-    // gross
-    let seulav: Vec<Vec<i32>> = values
-        .iter()
-        .map(|x| {
-            let mut x = x.clone();
-            x.reverse();
-            x
-        })
-        .collect();
     println!(
         "Part 2 answer: {}",
-        seulav
-            .iter()
-            .map(|x| x.last().unwrap() + predict(x))
-            .sum::<i32>(),
+        polynomials.iter().map(|x| x.at(-1)).sum::<BigInt>(),
+    );
+}
+
+#[test]
+fn test_sample_polynomials() {
+    let polynomials: Vec<Polynomial> = include_str!("../../sample/09.txt")
+        .lines()
+        .map(|line| {
+            let history: Vec<i64> =
+                line.split(' ').map(|x| x.parse().unwrap()).collect();
+            Polynomial::fit(&history).unwrap()
+        })
+        .collect();
+    assert_eq!(
+        polynomials.iter().map(|x| x.degree()).collect::<Vec<_>>(),
+        [1, 2, 3]
     );
+    let sum_at =
+        |index| polynomials.iter().map(|x| x.at(index)).sum::<BigInt>();
+    assert_eq!(sum_at(6), 114.into());
+    assert_eq!(sum_at(-1), 2.into());
+    // 0 3 6 ... is 3n, and 1 3 6 10 ... is (n + 1)(n + 2) / 2.
+    assert_eq!(polynomials[0].at(-1000), (-3000).into());
+    let n = BigInt::from(1_000_000_000);
+    assert_eq!(polynomials[1].at(1_000_000_000), (&n + 1) * (&n + 2) / 2);
+    assert!(Polynomial::fit(&[1, 2, 4, 8]).is_err());
+    assert_eq!(Polynomial::fit(&[0, 0]).unwrap().at(7), 0.into());
 }
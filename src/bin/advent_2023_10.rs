@@ -1,6 +1,11 @@
 use std::fmt::{Display, Formatter, Result as FmtResult};
 
-use advent_2023::{Point, Tilemap};
+use advent_2023::{
+    bitgrid::BitGrid,
+    image::{write_pixels, Pixels, Rgb},
+    polygon::LatticePolygon,
+    Point, Tilemap,
+};
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
 enum Pipe {
@@ -25,22 +30,36 @@ impl Pipe {
             Pipe::UpRight => &[Point { x: 0, y: -1 }, Point { x: 1, y: 0 }],
             Pipe::DownLeft => &[Point { x: 0, y: 1 }, Point { x: -1, y: 0 }],
             Pipe::DownRight => &[Point { x: 0, y: 1 }, Point { x: 1, y: 0 }],
-            Pipe::Snarbolax => panic!("Snarbolax is not connected!"),
+            // Until we work out what it's sitting on, anyway.
+            Pipe::Snarbolax => &[],
         }
     }
+    /// Reads either the puzzle's ASCII or the box drawing `Display` uses.
     fn from_char(c: char) -> Option<Pipe> {
         match c {
-            '.' => Some(Pipe::None),
-            '-' => Some(Pipe::Horizontal),
-            '|' => Some(Pipe::Vertical),
-            'F' => Some(Pipe::DownRight),
-            '7' => Some(Pipe::DownLeft),
-            'L' => Some(Pipe::UpRight),
-            'J' => Some(Pipe::UpLeft),
-            'S' => Some(Pipe::Snarbolax),
+            '.' | '·' => Some(Pipe::None),
+            '-' | '─' => Some(Pipe::Horizontal),
+            '|' | '│' => Some(Pipe::Vertical),
+            'F' | '┌' => Some(Pipe::DownRight),
+            '7' | '┐' => Some(Pipe::DownLeft),
+            'L' | '└' => Some(Pipe::UpRight),
+            'J' | '┘' => Some(Pipe::UpLeft),
+            'S' | '☺' => Some(Pipe::Snarbolax),
             _ => None,
         }
     }
+    fn to_ascii(self) -> char {
+        match self {
+            Pipe::None => '.',
+            Pipe::Horizontal => '-',
+            Pipe::Vertical => '|',
+            Pipe::UpLeft => 'J',
+            Pipe::UpRight => 'L',
+            Pipe::DownLeft => '7',
+            Pipe::DownRight => 'F',
+            Pipe::Snarbolax => 'S',
+        }
+    }
 }
 
 impl Display for Pipe {
@@ -57,18 +76,7 @@ impl Display for Pipe {
                 Pipe::DownLeft => '┐',
                 Pipe::DownRight => '┌',
                 Pipe::Snarbolax => '☺',
-            } /*
-              match self {
-                  Pipe::None => '.',
-                  Pipe::Horizontal => '-',
-                  Pipe::Vertical => '|',
-                  Pipe::UpLeft => 'J',
-                  Pipe::UpRight => 'L',
-                  Pipe::DownLeft => '7',
-                  Pipe::DownRight => 'F',
-                  Pipe::Snarbolax => 'S',
-              }
-              */
+            }
         )
     }
 }
@@ -79,42 +87,63 @@ const ALL_CONNECTIONS: &[Point] = &[
     Point { x: 0, y: -1 },
     Point { x: 0, y: 1 },
 ];
-/// Returns the location at which we found Snarbolax.
-fn peer_through_snarbolax(tilemap: &mut Tilemap<Pipe>) -> Point {
-    let snarbolax_pos = tilemap
-        .find_tile(|x| *x == Pipe::Snarbolax)
-        .expect("Where is my Snarbie!!??");
-    let mut connectibles = Vec::with_capacity(4);
-    for connection in ALL_CONNECTIONS {
-        let neighbor = snarbolax_pos + *connection;
-        if let Some(pipe) = tilemap.get_tile(neighbor) {
-            if pipe.get_connections().iter().any(|x| *x == -*connection) {
-                connectibles.push(*connection);
+
+const ALL_PIPES: &[Pipe] = &[
+    Pipe::Horizontal,
+    Pipe::Vertical,
+    Pipe::UpLeft,
+    Pipe::UpRight,
+    Pipe::DownLeft,
+    Pipe::DownRight,
+];
+
+/// Works out what pipe each Snarbolax is hiding and puts it there. If only
+/// one pipe fits the neighbors, that's it. Otherwise it's whichever one
+/// closes a loop, and if none do, there was never a pipe there at all.
+/// Returns where the Snarbolaxes were.
+fn peer_through_snarbolaxes(tilemap: &mut Tilemap<Pipe>) -> Vec<Point> {
+    let positions: Vec<Point> =
+        tilemap.find_tiles(|x| *x == Pipe::Snarbolax).collect();
+    let mut undecided = vec![];
+    for snarbolax_pos in positions.iter().copied() {
+        let mut connectibles = Vec::with_capacity(4);
+        for connection in ALL_CONNECTIONS {
+            let neighbor = snarbolax_pos + *connection;
+            if let Some(pipe) = tilemap.get_tile(neighbor) {
+                if pipe.get_connections().contains(&-*connection) {
+                    connectibles.push(*connection);
+                }
             }
         }
+        let candidates: Vec<Pipe> = ALL_PIPES
+            .iter()
+            .copied()
+            .filter(|pipe| {
+                pipe.get_connections()
+                    .iter()
+                    .all(|x| connectibles.contains(x))
+            })
+            .collect();
+        match candidates[..] {
+            [] => tilemap.set_tile(snarbolax_pos, Pipe::None),
+            [pipe] => tilemap.set_tile(snarbolax_pos, pipe),
+            _ => undecided.push((snarbolax_pos, candidates)),
+        }
     }
-    assert_eq!(
-        connectibles.len(),
-        2,
-        "Snarbolax must be connected to exactly two pipes!"
-    );
-    let top = connectibles.iter().any(|x| *x == Point { x: 0, y: -1 });
-    let bottom = connectibles.iter().any(|x| *x == Point { x: 0, y: 1 });
-    let left = connectibles.iter().any(|x| *x == Point { x: -1, y: 0 });
-    let right = connectibles.iter().any(|x| *x == Point { x: 1, y: 0 });
-    let underpipe = match (top, bottom, left, right) {
-        (true, true, false, false) => Pipe::Vertical,
-        (false, false, true, true) => Pipe::Horizontal,
-        (true, false, true, false) => Pipe::UpLeft,
-        (false, true, false, true) => Pipe::DownRight,
-        (true, false, false, true) => Pipe::UpRight,
-        (false, true, true, false) => Pipe::DownLeft,
-        _ => panic!("Snarbolax caused non-euclidean existence again: {top:?} {bottom:?} {left:?} {right:?}"),
-    };
-    tilemap.set_tile(snarbolax_pos, underpipe);
-    // Make sure there was only one Snarbolax (dear lord)
-    assert!(tilemap.find_tile(|x| *x == Pipe::Snarbolax).is_none());
-    snarbolax_pos
+    for (snarbolax_pos, candidates) in undecided {
+        let mut visited =
+            Tilemap::new(tilemap.get_width(), tilemap.get_height());
+        let underpipe = candidates
+            .into_iter()
+            .find(|pipe| {
+                tilemap.set_tile(snarbolax_pos, *pipe);
+                visited.iter_mut().for_each(|x| *x = false);
+                trace_loop(tilemap, snarbolax_pos, &mut visited).is_some()
+            })
+            .unwrap_or(Pipe::None);
+        tilemap.set_tile(snarbolax_pos, underpipe);
+    }
+    positions
 }
 
 fn follow_pipe(end: Point, prev: Point, tilemap: &Tilemap<Pipe>) -> Point {
@@ -129,54 +158,99 @@ fn follow_pipe(end: Point, prev: Point, tilemap: &Tilemap<Pipe>) -> Point {
     }
 }
 
-fn main() {
-    let lines = std::io::stdin().lines().map(|x| x.unwrap());
-    let mut tilemap = Tilemap::new_empty();
-    let mut buf: Vec<Pipe> = vec![];
-    for line in lines {
-        buf.clear();
-        buf.extend(line.chars().map(Pipe::from_char).map(|x| x.unwrap()));
-        tilemap.add_row(&buf);
+/// Follows the pipe at `start` all the way around, marking everything it
+/// walks over as visited. Returns the tiles in order if it gets back to
+/// `start`, or `None` if the pipe leads off the map, into a pipe that
+/// doesn't connect back, or into something already visited.
+fn trace_loop(
+    tilemap: &Tilemap<Pipe>,
+    start: Point,
+    visited: &mut Tilemap<bool>,
+) -> Option<Vec<Point>> {
+    let connections = tilemap.get_tile(start)?.get_connections();
+    if connections.len() != 2 {
+        return None;
     }
-    let snarbolax_pos = peer_through_snarbolax(&mut tilemap);
-    let snarbolax_pipe = *tilemap.get_tile(snarbolax_pos).unwrap();
-    // Am I gonna regret that I mutated Snarbolax away...? NOPE!
-    let mut loopmap = Tilemap::new(tilemap.get_width(), tilemap.get_height());
-    loopmap.set_tile(snarbolax_pos, snarbolax_pipe);
-    let ends = snarbolax_pipe.get_connections();
-    assert_eq!(ends.len(), 2);
-    let mut ends = [snarbolax_pos + ends[0], snarbolax_pos + ends[1]];
-    let mut prevs = [snarbolax_pos, snarbolax_pos];
-    let mut distance = 1;
-    while ends[0] != ends[1] {
-        loopmap.set_tile(ends[0], *tilemap.get_tile(ends[0]).unwrap());
-        loopmap.set_tile(ends[1], *tilemap.get_tile(ends[1]).unwrap());
-        (prevs[0], ends[0]) =
-            (ends[0], follow_pipe(ends[0], prevs[0], &tilemap));
-        (prevs[1], ends[1]) =
-            (ends[1], follow_pipe(ends[1], prevs[1], &tilemap));
-        distance += 1;
-    }
-    loopmap.set_tile(ends[0], *tilemap.get_tile(ends[0]).unwrap());
-    println!("Puzzle 1 answer: {distance}");
-    let mut total = 0;
-    let mut insidemap = loopmap.clone();
+    visited.set_tile(start, true);
+    let mut tiles = vec![start];
+    let mut prev = start;
+    let mut end = start + connections[0];
+    while end != start {
+        let pipe = tilemap.get_tile(end)?;
+        if *visited.get_tile(end).unwrap()
+            || !pipe.get_connections().contains(&(prev - end))
+        {
+            return None;
+        }
+        visited.set_tile(end, true);
+        tiles.push(end);
+        (prev, end) = (end, follow_pipe(end, prev, tilemap));
+    }
+    Some(tiles)
+}
+
+/// A closed loop of pipe.
+struct PipeLoop {
+    /// Every tile of the loop, in order.
+    tiles: Vec<Point>,
+    /// Measured through the middles of the tiles.
+    polygon: LatticePolygon,
+    /// Every tile inside the loop that isn't part of it.
+    enclosed: Vec<Point>,
+}
+
+impl PipeLoop {
+    fn new(tilemap: &Tilemap<Pipe>, tiles: Vec<Point>) -> PipeLoop {
+        let polygon = LatticePolygon::new(
+            tiles.iter().map(|x| (x.x as i64, x.y as i64)),
+        );
+        // Only the loop's bounding box can have anything inside it, so
+        // that's all that needs scanning.
+        let min = Point {
+            x: tiles.iter().map(|x| x.x).min().unwrap(),
+            y: tiles.iter().map(|x| x.y).min().unwrap(),
+        };
+        let max = Point {
+            x: tiles.iter().map(|x| x.x).max().unwrap(),
+            y: tiles.iter().map(|x| x.y).max().unwrap(),
+        };
+        let mut loopmap = Tilemap::new(max.x - min.x + 1, max.y - min.y + 1);
+        for point in tiles.iter() {
+            loopmap.set_tile(*point - min, *tilemap.get_tile(*point).unwrap());
+        }
+        let enclosed =
+            scan_inside(&loopmap).into_iter().map(|x| x + min).collect();
+        PipeLoop {
+            tiles,
+            polygon,
+            enclosed,
+        }
+    }
+    /// How many steps it is to the far side of the loop.
+    fn farthest(&self) -> usize {
+        self.tiles.len() / 2
+    }
+    /// Loops on a grid always have an even length, so this comes out even.
+    fn area(&self) -> u128 {
+        self.polygon.double_area / 2
+    }
+}
+
+/// Finds the tiles inside a map with nothing but one loop on it, by
+/// scanning each row and keeping track of whether the top and bottom halves
+/// of the tile are inside.
+fn scan_inside(loopmap: &Tilemap<Pipe>) -> Vec<Point> {
+    let mut ret = vec![];
     for (y, row) in loopmap.rows().enumerate() {
         let mut inside_up = false;
         let mut inside_down = false;
         for (x, pipe) in row.iter().enumerate() {
             match *pipe {
-                Pipe::None => {
-                    if inside_up && inside_down {
-                        insidemap.set_tile(
-                            Point {
-                                x: x as i32,
-                                y: y as i32,
-                            },
-                            Pipe::Snarbolax,
-                        );
-                        total += 1;
-                    }
+                Pipe::None if inside_up && inside_down => {
+                    ret.push(Point {
+                        x: x as i32,
+                        y: y as i32,
+                    });
                 }
                 Pipe::Vertical => {
                     inside_up = !inside_up;
@@ -188,6 +262,309 @@ fn main() {
             }
         }
     }
-    println!("{insidemap}");
-    println!("Puzzle 2 answer: {total}");
+    ret
+}
+
+/// Every closed loop on the map, in the order their first tiles come up
+/// reading top to bottom.
+fn find_loops(tilemap: &Tilemap<Pipe>) -> Vec<PipeLoop> {
+    let mut visited = Tilemap::new(tilemap.get_width(), tilemap.get_height());
+    let mut ret = vec![];
+    for y in 0..tilemap.get_height() {
+        for x in 0..tilemap.get_width() {
+            let point = Point { x, y };
+            if *visited.get_tile(point).unwrap() {
+                continue;
+            }
+            if let Some(tiles) = trace_loop(tilemap, point, &mut visited) {
+                ret.push(PipeLoop::new(tilemap, tiles));
+            }
+        }
+    }
+    ret
+}
+
+/// Draws every loop, with a Snarbolax on each tile inside one.
+fn draw_insides(tilemap: &Tilemap<Pipe>, loops: &[PipeLoop]) -> Tilemap<Pipe> {
+    let mut insidemap =
+        Tilemap::new(tilemap.get_width(), tilemap.get_height());
+    for pipe_loop in loops.iter() {
+        for point in pipe_loop.tiles.iter() {
+            insidemap.set_tile(*point, *tilemap.get_tile(*point).unwrap());
+        }
+    }
+    for pipe_loop in loops.iter() {
+        for point in pipe_loop.enclosed.iter() {
+            if *insidemap.get_tile(*point).unwrap() == Pipe::None {
+                insidemap.set_tile(*point, Pipe::Snarbolax);
+            }
+        }
+    }
+    insidemap
+}
+
+const OUTSIDE_COLOR: Rgb = [16, 16, 16];
+const PIPE_COLOR: Rgb = [192, 192, 192];
+const INSIDE_COLOR: Rgb = [64, 160, 64];
+
+/// Writes `draw_insides` out as a picture (PNG or PPM, by extension). Each
+/// tile becomes a 3×3 block with its pipe drawn through the middle, and the
+/// tiles inside a loop are filled in.
+fn write_inside_image(
+    path: &str,
+    insidemap: &Tilemap<Pipe>,
+) -> std::io::Result<()> {
+    let mut pixels: Pixels = Tilemap::new_with(
+        OUTSIDE_COLOR,
+        insidemap.get_width() * 3,
+        insidemap.get_height() * 3,
+    );
+    for pos in insidemap.find_tiles(|x| *x != Pipe::None) {
+        let center = Point {
+            x: pos.x * 3 + 1,
+            y: pos.y * 3 + 1,
+        };
+        match insidemap.get_tile(pos).unwrap() {
+            // That's how `draw_insides` marks the inside.
+            Pipe::Snarbolax => {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        pixels.set_tile(
+                            center + Point { x: dx, y: dy },
+                            INSIDE_COLOR,
+                        );
+                    }
+                }
+            }
+            pipe => {
+                pixels.set_tile(center, PIPE_COLOR);
+                for connection in pipe.get_connections() {
+                    pixels.set_tile(center + *connection, PIPE_COLOR);
+                }
+            }
+        }
+    }
+    write_pixels(path, &pixels)
+}
+
+/// Works out the same thing as `draw_insides`, but the way the puzzle
+/// describes it: by squeezing between pipes. Each tile is blown up to 3×3
+/// with its pipe drawn through the middle, so two pipes side by side have a
+/// real gap between them. Everywhere reachable from the edge of that is
+/// outside, and tiles whose middles can't be reached are inside.
+fn squeeze_between_pipes(
+    tilemap: &Tilemap<Pipe>,
+    loops: &[PipeLoop],
+) -> Tilemap<Pipe> {
+    let width = tilemap.get_width() * 3;
+    let height = tilemap.get_height() * 3;
+    let mut walls = BitGrid::new(width, height);
+    for point in loops.iter().flat_map(|x| x.tiles.iter()) {
+        let middle = Point {
+            x: point.x * 3 + 1,
+            y: point.y * 3 + 1,
+        };
+        walls.set_tile(middle, true);
+        for connection in tilemap.get_tile(*point).unwrap().get_connections() {
+            walls.set_tile(middle + *connection, true);
+        }
+    }
+    let mut outside = BitGrid::new(width, height);
+    let mut stack: Vec<Point> = (0..width)
+        .flat_map(|x| [Point { x, y: 0 }, Point { x, y: height - 1 }])
+        .chain(
+            (0..height)
+                .flat_map(|y| [Point { x: 0, y }, Point { x: width - 1, y }]),
+        )
+        .collect();
+    while let Some(point) = stack.pop() {
+        if walls.get_tile(point) != Some(false)
+            || outside.get_tile(point) != Some(false)
+        {
+            continue;
+        }
+        outside.set_tile(point, true);
+        stack.extend(ALL_CONNECTIONS.iter().map(|x| point + *x));
+    }
+    let mut squeezemap = tilemap.clone();
+    for y in 0..tilemap.get_height() {
+        for x in 0..tilemap.get_width() {
+            let point = Point { x, y };
+            let middle = Point {
+                x: x * 3 + 1,
+                y: y * 3 + 1,
+            };
+            if walls.get_tile(middle).unwrap() {
+                continue;
+            }
+            squeezemap.set_tile(
+                point,
+                if outside.get_tile(middle).unwrap() {
+                    Pipe::None
+                } else {
+                    Pipe::Snarbolax
+                },
+            );
+        }
+    }
+    squeezemap
+}
+
+fn read_tilemap(input: &str) -> Tilemap<Pipe> {
+    let mut tilemap = Tilemap::new_empty();
+    let mut buf: Vec<Pipe> = vec![];
+    for line in input.lines() {
+        buf.clear();
+        buf.extend(line.chars().map(|x| {
+            Pipe::from_char(x).unwrap_or_else(|| panic!("Bad pipe: {x:?}"))
+        }));
+        tilemap.add_row(&buf);
+    }
+    tilemap
+}
+
+fn main() {
+    let mut convert = None;
+    let mut squeeze = false;
+    let mut image_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--squeeze" => squeeze = true,
+            "--image" => image_path = args.next(),
+            "--to-ascii" => convert = Some(true),
+            "--to-unicode" => convert = Some(false),
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
+    let input = std::io::read_to_string(std::io::stdin()).unwrap();
+    let mut tilemap = read_tilemap(&input);
+    if let Some(ascii) = convert {
+        for row in tilemap.rows() {
+            let line: String = if ascii {
+                row.iter().map(|x| x.to_ascii()).collect()
+            } else {
+                row.iter().map(|x| x.to_string()).collect()
+            };
+            println!("{line}");
+        }
+        return;
+    }
+    let snarbolaxes = peer_through_snarbolaxes(&mut tilemap);
+    // Am I gonna regret that I mutated Snarbolax away...? NOPE!
+    let loops = find_loops(&tilemap);
+    for (index, pipe_loop) in loops.iter().enumerate() {
+        println!(
+            "Loop {} at {:?}: {} long, area {}, {} tiles enclosed",
+            index + 1,
+            pipe_loop.tiles[0],
+            pipe_loop.tiles.len(),
+            pipe_loop.area(),
+            pipe_loop.enclosed.len(),
+        );
+    }
+    let insidemap = draw_insides(&tilemap, &loops);
+    if squeeze {
+        let squeezemap = squeeze_between_pipes(&tilemap, &loops);
+        println!("{squeezemap}");
+        assert_eq!(
+            squeezemap, insidemap,
+            "Squeezing between pipes disagrees with the scan!"
+        );
+    } else {
+        println!("{insidemap}");
+    }
+    if let Some(path) = image_path {
+        write_inside_image(&path, &insidemap).unwrap();
+    }
+    // The puzzle is about the loop Snarbolax is on, but if there's no
+    // Snarbolax, the biggest loop will do.
+    let main_loop = loops
+        .iter()
+        .find(|x| x.tiles.iter().any(|x| snarbolaxes.contains(x)))
+        .or_else(|| loops.iter().max_by_key(|x| x.tiles.len()))
+        .expect("There are no loops!");
+    println!("Puzzle 1 answer: {}", main_loop.farthest());
+    println!("Puzzle 2 answer: {}", main_loop.enclosed.len());
+}
+
+#[test]
+fn test_loops_without_snarbolax() {
+    // A loop inside another one, and a bit of pipe that goes nowhere.
+    let tilemap = read_tilemap(
+        "F----7\n\
+         |F-7.|\n\
+         |L-J-|\n\
+         L----J",
+    );
+    let loops = find_loops(&tilemap);
+    assert_eq!(loops.len(), 2);
+    assert_eq!(loops[0].tiles.len(), 16);
+    assert_eq!(loops[0].area(), 15);
+    assert_eq!(loops[0].enclosed.len(), 8);
+    assert_eq!(loops[1].tiles.len(), 6);
+    assert_eq!(loops[1].enclosed.len(), 0);
+    let ascii: String =
+        tilemap.rows().flatten().map(|x| x.to_ascii()).collect();
+    let unicode: String =
+        tilemap.rows().flatten().map(|x| x.to_string()).collect();
+    assert_eq!(
+        read_tilemap(&unicode)
+            .rows()
+            .flatten()
+            .map(|x| x.to_ascii())
+            .collect::<String>(),
+        ascii
+    );
+}
+
+#[test]
+fn test_squeeze_matches_scan() {
+    let mut tilemap = read_tilemap(
+        "..........\n\
+         .S------7.\n\
+         .|F----7|.\n\
+         .||....||.\n\
+         .||....||.\n\
+         .|L-7F-J|.\n\
+         .|..||..|.\n\
+         .L--JL--J.\n\
+         ..........",
+    );
+    peer_through_snarbolaxes(&mut tilemap);
+    let loops = find_loops(&tilemap);
+    let squeezemap = squeeze_between_pipes(&tilemap, &loops);
+    assert_eq!(squeezemap, draw_insides(&tilemap, &loops));
+    assert_eq!(squeezemap.find_tiles(|x| *x == Pipe::Snarbolax).count(), 4);
+}
+
+#[test]
+fn test_scan_matches_polygon() {
+    // Pick's theorem and the scan ought to agree on every loop, wherever it
+    // is on the map.
+    let tilemap = read_tilemap(
+        "...F-7....\n\
+         .F-J.|.F7.\n\
+         .|...L-J|.\n\
+         .L------J.\n\
+         ....F7....\n\
+         ....LJ....",
+    );
+    let loops = find_loops(&tilemap);
+    assert_eq!(loops.len(), 2);
+    for pipe_loop in loops.iter() {
+        assert_eq!(
+            pipe_loop.polygon.interior,
+            pipe_loop.enclosed.len() as u128
+        );
+    }
+    assert_eq!(
+        loops[0].enclosed,
+        [
+            Point { x: 4, y: 1 },
+            Point { x: 2, y: 2 },
+            Point { x: 3, y: 2 },
+            Point { x: 4, y: 2 },
+        ]
+    );
 }
@@ -1,42 +1,126 @@
-use std::collections::HashSet;
-
 use advent_2023::{Point, Tilemap};
 
-fn find_expanded_path_distance(
-    galaxies: &[Point],
-    blank_rows: &HashSet<i32>,
-    blank_columns: &HashSet<i32>,
-    expansion_factor: i32,
-) -> i64 {
-    galaxies
-        .iter()
-        .map(|a| {
-            galaxies
-                .iter()
-                .map(|b| {
-                    // This is harmless if done on the same galaxy in both a and b,
-                    // because the distance would still be zero.
-                    let d = *a - *b;
-                    let mut ret = (d.x.abs() + d.y.abs()) as i64;
-                    for x in a.x.min(b.x) + 1..a.x.max(b.x) {
-                        if blank_columns.contains(&x) {
-                            ret += expansion_factor as i64;
-                        }
-                    }
-                    for y in a.y.min(b.y) + 1..a.y.max(b.y) {
-                        if blank_rows.contains(&y) {
-                            ret += expansion_factor as i64;
-                        }
-                    }
+/// Every galaxy's position along one axis after the universe expands,
+/// sorted, with running totals so that distances can be added up without
+/// visiting every pair of galaxies.
+struct SortedAxis {
+    coords: Vec<u128>,
+    /// `prefix[i]` is the sum of the first `i` coordinates.
+    prefix: Vec<u128>,
+}
+
+impl SortedAxis {
+    fn new(mut coords: Vec<u128>) -> SortedAxis {
+        coords.sort_unstable();
+        let mut prefix = Vec::with_capacity(coords.len() + 1);
+        prefix.push(0);
+        for coord in coords.iter() {
+            prefix.push(prefix.last().unwrap() + coord);
+        }
+        SortedAxis { coords, prefix }
+    }
+    /// The sum of the distances between every pair of galaxies along this
+    /// axis. The `i`th galaxy is past each of the `i` before it, by its
+    /// coordinate minus theirs.
+    fn pairwise(&self) -> u128 {
+        self.coords
+            .iter()
+            .enumerate()
+            .map(|(i, coord)| coord * i as u128 - self.prefix[i])
+            .sum()
+    }
+    /// The sum of the distances from `at` to every galaxy along this axis.
+    fn distances_from(&self, at: u128) -> u128 {
+        let n = self.coords.len();
+        let i = self.coords.partition_point(|x| *x < at);
+        let below = at * i as u128 - self.prefix[i];
+        let above = (self.prefix[n] - self.prefix[i]) - at * (n - i) as u128;
+        below + above
+    }
+}
+
+/// The galaxies, spread out so that every empty row and column is now
+/// `expansion_factor` rows or columns.
+struct Universe {
+    galaxies: Vec<(u128, u128)>,
+    xs: SortedAxis,
+    ys: SortedAxis,
+}
+
+impl Universe {
+    fn new(galaxymap: &Tilemap<bool>, expansion_factor: u64) -> Universe {
+        assert!(
+            expansion_factor > 0,
+            "The universe can't expand to nothing!"
+        );
+        let galaxies: Vec<Point> = galaxymap.find_tiles(|x| *x).collect();
+        // Where each column and row ends up, counting the growth of every
+        // empty one before it.
+        let mut full_columns = vec![false; galaxymap.get_width() as usize];
+        let mut full_rows = vec![false; galaxymap.get_height() as usize];
+        for galaxy in galaxies.iter() {
+            full_columns[galaxy.x as usize] = true;
+            full_rows[galaxy.y as usize] = true;
+        }
+        let expand = |full: &[bool]| -> Vec<u128> {
+            let mut at = 0u128;
+            full.iter()
+                .map(|full| {
+                    let ret = at;
+                    at += if *full { 1 } else { expansion_factor as u128 };
                     ret
                 })
-                .sum::<i64>()
-        })
-        .sum::<i64>()
-        / 2 // ...
+                .collect()
+        };
+        let new_x = expand(&full_columns);
+        let new_y = expand(&full_rows);
+        let galaxies: Vec<(u128, u128)> = galaxies
+            .iter()
+            .map(|x| (new_x[x.x as usize], new_y[x.y as usize]))
+            .collect();
+        Universe {
+            xs: SortedAxis::new(galaxies.iter().map(|x| x.0).collect()),
+            ys: SortedAxis::new(galaxies.iter().map(|x| x.1).collect()),
+            galaxies,
+        }
+    }
+    /// The sum of the shortest paths between every pair of galaxies.
+    fn total_distance(&self) -> u128 {
+        self.xs.pairwise() + self.ys.pairwise()
+    }
+    /// The sum of the shortest paths from one galaxy to all the others.
+    /// Galaxies are numbered from 0, reading top to bottom.
+    fn distances_from(&self, galaxy: usize) -> u128 {
+        let (x, y) = self.galaxies[galaxy];
+        self.xs.distances_from(x) + self.ys.distances_from(y)
+    }
 }
 
 fn main() {
+    let mut expansion_factor = None;
+    let mut galaxy = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--expansion" => {
+                expansion_factor = Some(
+                    args.next()
+                        .expect("--expansion needs a number")
+                        .parse::<u64>()
+                        .expect("--expansion needs a number"),
+                )
+            }
+            "--galaxy" => {
+                galaxy = Some(
+                    args.next()
+                        .expect("--galaxy needs a number")
+                        .parse::<usize>()
+                        .expect("--galaxy needs a number"),
+                )
+            }
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let mut galaxymap = Tilemap::new_empty();
     let mut buf: Vec<bool> = vec![];
@@ -45,31 +129,64 @@ fn main() {
         buf.extend(line.chars().map(|x| x == '#'));
         galaxymap.add_row(&buf);
     }
-    // Find all galaxy-less rows
-    let blank_rows: HashSet<i32> = (0..galaxymap.get_height())
-        .filter(|y| galaxymap.get_row(*y).unwrap().iter().all(|x| !x))
-        .collect();
-    // Find all galaxy-less columns
-    let blank_columns: HashSet<i32> = (0..galaxymap.get_width())
-        .filter(|x| {
-            (0..galaxymap.get_height())
-                .map(|y| *galaxymap.get_tile(Point { x: *x, y }).unwrap())
-                .all(|x| !x)
-        })
-        .collect();
-    // Find every galaxy
-    let galaxies: Vec<Point> = galaxymap.find_tiles(|x| *x).collect();
+    if let Some(expansion_factor) = expansion_factor {
+        let universe = Universe::new(&galaxymap, expansion_factor);
+        match galaxy {
+            // The puzzle numbers galaxies from 1.
+            Some(galaxy) => {
+                assert!(
+                    (1..=universe.galaxies.len()).contains(&galaxy),
+                    "There's no galaxy {galaxy}"
+                );
+                println!(
+                    "Galaxy {galaxy} to every other: {}",
+                    universe.distances_from(galaxy - 1)
+                );
+            }
+            None => println!(
+                "Expanded by {expansion_factor}: {}",
+                universe.total_distance()
+            ),
+        }
+        return;
+    }
+    assert!(galaxy.is_none(), "--galaxy needs an --expansion");
     println!(
         "Puzzle 1 answer: {}",
-        find_expanded_path_distance(&galaxies, &blank_rows, &blank_columns, 1)
+        Universe::new(&galaxymap, 2).total_distance()
     );
     println!(
         "Puzzle 2 answer: {}",
-        find_expanded_path_distance(
-            &galaxies,
-            &blank_rows,
-            &blank_columns,
-            999_999
-        )
+        Universe::new(&galaxymap, 1_000_000).total_distance()
+    );
+}
+
+#[test]
+fn test_sample_expansions() {
+    let mut galaxymap = Tilemap::new_empty();
+    for line in include_str!("../../sample/11.txt").lines() {
+        let row: Vec<bool> = line.chars().map(|x| x == '#').collect();
+        galaxymap.add_row(&row);
+    }
+    assert_eq!(Universe::new(&galaxymap, 2).total_distance(), 374);
+    assert_eq!(Universe::new(&galaxymap, 10).total_distance(), 1030);
+    assert_eq!(Universe::new(&galaxymap, 100).total_distance(), 8410);
+    let universe = Universe::new(&galaxymap, 2);
+    let brute_force = |a: usize| -> u128 {
+        let (ax, ay) = universe.galaxies[a];
+        universe
+            .galaxies
+            .iter()
+            .map(|(bx, by)| ax.abs_diff(*bx) + ay.abs_diff(*by))
+            .sum()
+    };
+    for galaxy in 0..universe.galaxies.len() {
+        assert_eq!(universe.distances_from(galaxy), brute_force(galaxy));
+    }
+    assert_eq!(
+        (0..universe.galaxies.len())
+            .map(|x| universe.distances_from(x))
+            .sum::<u128>(),
+        374 * 2
     );
 }
@@ -113,6 +113,7 @@ impl FromStr for SpringRow {
     }
 }
 
+#[cfg(test)]
 fn evaluate_condition(states: &[State]) -> Vec<usize> {
     // overshoot and you'll hit the moon!
     let mut ret = Vec::with_capacity(states.len());
@@ -138,6 +139,7 @@ fn evaluate_condition(states: &[State]) -> Vec<usize> {
 }
 
 impl SpringRow {
+    #[cfg(test)]
     fn brute_force_count_permutations(&self) -> usize {
         let mut hypothetical: Vec<State> = self
             .states
@@ -154,16 +156,18 @@ impl SpringRow {
                 ret += 1;
             }
             let mut stop = true;
-            for n in 0..hypothetical.len() {
-                if self.states[n] == State::Unknown {
-                    match hypothetical[n] {
+            for (state, guess) in
+                self.states.iter().zip(hypothetical.iter_mut())
+            {
+                if *state == State::Unknown {
+                    match *guess {
                         State::Operational => {
-                            hypothetical[n] = State::Damaged;
+                            *guess = State::Damaged;
                             stop = false;
                             break;
                         }
                         State::Damaged => {
-                            hypothetical[n] = State::Operational;
+                            *guess = State::Operational;
                             // do not break
                         }
                         State::Unknown => unreachable!(),
@@ -1,116 +1,191 @@
-use std::collections::HashSet;
+use advent_2023::{
+    bitgrid::{set_bits, BitGrid},
+    Point,
+};
 
-use advent_2023::{Point, Tilemap};
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Mirror {
+    /// Between two columns, with this many columns to its left.
+    Vertical(usize),
+    /// Between two rows, with this many rows above it.
+    Horizontal(usize),
+}
 
-fn get_all_reflections(
-    slice: &[bool],
-    ignored_candidate: Option<usize>,
-) -> HashSet<usize> {
-    (1..slice.len())
-        .filter(|candidate_x| {
-            if Some(*candidate_x) == ignored_candidate {
-                return false;
-            }
-            for x in 0..*candidate_x {
-                let reflected_x = candidate_x + (candidate_x - x) - 1;
-                if reflected_x >= slice.len() {
-                    continue;
-                }
-                if slice[reflected_x] != slice[x] {
-                    return false;
-                }
-            }
-            true
-        })
-        .collect()
+impl Mirror {
+    fn summarize(&self) -> usize {
+        match self {
+            Mirror::Vertical(x) => *x,
+            Mirror::Horizontal(y) => y * 100,
+        }
+    }
+    /// Where bit `bit` of line `line` is, with lines being columns for a
+    /// vertical mirror and rows for a horizontal one.
+    fn point(&self, line: i32, bit: u32) -> Point {
+        match self {
+            Mirror::Vertical(_) => Point {
+                x: line,
+                y: bit as i32,
+            },
+            Mirror::Horizontal(_) => Point {
+                x: bit as i32,
+                y: line,
+            },
+        }
+    }
 }
 
-fn get_h_reflection(
-    tilemap: &Tilemap<bool>,
-    ignored_candidate: Option<usize>,
-) -> Option<usize> {
-    let mut reflections: Option<HashSet<usize>> = None;
-    for y in 0..tilemap.get_height() {
-        let new_reflections = get_all_reflections(
-            tilemap.get_row(y).unwrap(),
-            ignored_candidate,
-        );
-        if let Some(reflections) = reflections.as_mut() {
-            reflections.retain(|x| new_reflections.contains(x));
-        } else {
-            reflections = Some(new_reflections);
+/// A mirror, along with every pair of tiles that don't match across it.
+/// Cleaning either tile of a pair fixes that smudge.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Reflection {
+    mirror: Mirror,
+    smudges: Vec<(Point, Point)>,
+}
+
+/// Two lines that ought to be reflections of each other, and the bits they
+/// differ in.
+type Difference = (i32, i32, u64);
+
+/// Tries every line between two of the `count` lines of bits (rows or
+/// columns, as integers), XORing each pair of lines that would reflect onto
+/// each other and counting the bits that differ. Returns every candidate
+/// that's off by exactly `smudges` bits, as how many lines come before it
+/// along with the pairs of lines that differ and the bits they differ in.
+fn find_reflections(
+    count: i32,
+    line: impl Fn(i32) -> u64,
+    smudges: u32,
+) -> Vec<(usize, Vec<Difference>)> {
+    let mut ret = vec![];
+    'candidates: for candidate in 1..count {
+        let mut differences = vec![];
+        let mut total = 0;
+        for (a, b) in (0..candidate).rev().zip(candidate..count) {
+            let diff = line(a) ^ line(b);
+            if diff != 0 {
+                total += diff.count_ones();
+                if total > smudges {
+                    continue 'candidates;
+                }
+                differences.push((a, b, diff));
+            }
+        }
+        if total == smudges {
+            ret.push((candidate as usize, differences));
         }
     }
-    reflections
-        .filter(|x| x.len() == 1)
-        .map(|reflections| *reflections.iter().next().unwrap())
+    ret
 }
 
-fn get_v_reflection(
-    tilemap: &Tilemap<bool>,
-    ignored_candidate: Option<usize>,
-) -> Option<usize> {
-    get_h_reflection(&tilemap.transpose(), ignored_candidate)
+/// The lines of the grid one way round: what kind of mirror goes between
+/// them, how many there are, and how to get one as bits.
+type Orientation =
+    (fn(usize) -> Mirror, i32, fn(&BitGrid, i32) -> Option<u64>);
+
+/// Every mirror in the pattern that would be perfect with exactly
+/// `smudges` tiles cleaned, and which tiles those are.
+fn get_reflections(grid: &BitGrid, smudges: u32) -> Vec<Reflection> {
+    let orientations: [Orientation; 2] = [
+        (Mirror::Vertical, grid.get_width(), BitGrid::column_bits),
+        (Mirror::Horizontal, grid.get_height(), BitGrid::row_bits),
+    ];
+    orientations
+        .into_iter()
+        .flat_map(|(mirror, count, line)| {
+            find_reflections(count, |x| line(grid, x).unwrap(), smudges)
+                .into_iter()
+                .map(move |(before, differences)| {
+                    let mirror = mirror(before);
+                    let smudges = differences
+                        .into_iter()
+                        .flat_map(|(a, b, diff)| {
+                            set_bits(diff).map(move |bit| {
+                                (mirror.point(a, bit), mirror.point(b, bit))
+                            })
+                        })
+                        .collect();
+                    Reflection { mirror, smudges }
+                })
+        })
+        .collect()
 }
 
-fn main() {
-    let lines = std::io::stdin().lines().map(|x| x.unwrap());
+/// Splits the input into its patterns, which have blank lines between them.
+fn parse_patterns(input: &str) -> Vec<BitGrid> {
     let mut buf = Vec::with_capacity(32); // 32 is a nice number.
-    let mut tilemaps: Vec<Tilemap<bool>> = vec![Tilemap::new_empty()];
-    for line in lines {
+    let mut tilemaps: Vec<BitGrid> = vec![BitGrid::new_empty()];
+    for line in input.lines() {
         if line.is_empty() {
-            tilemaps.push(Tilemap::new_empty());
+            tilemaps.push(BitGrid::new_empty());
         } else {
             buf.clear();
             buf.extend(line.chars().map(|x| x == '#'));
             tilemaps.last_mut().unwrap().add_row(&buf);
         }
     }
-    println!(
-        "Puzzle 1 answer: {}",
-        tilemaps
-            .iter()
-            .map(|x| {
-                get_h_reflection(x, None).unwrap_or(0)
-                    + get_v_reflection(x, None).map(|x| x * 100).unwrap_or(0)
-            })
-            .sum::<usize>()
-    );
-    println!(
-        "Puzzle 2 answer: {}",
-        tilemaps
-            .iter_mut()
-            .map(|tilemap| {
-                let mut different_answer = None;
-                let mut pamelit = tilemap.transpose();
-                let h_orig = get_h_reflection(tilemap, None);
-                let v_orig = get_h_reflection(&pamelit, None);
-                let same_answer =
-                    h_orig.unwrap_or(0) + v_orig.map(|x| x * 100).unwrap_or(0);
-                'outer: for y in 0..tilemap.get_height() {
-                    for x in 0..tilemap.get_width() {
-                        *tilemap.get_tile_mut(Point { x, y }).unwrap() ^= true;
-                        let h_answer =
-                            get_h_reflection(tilemap, h_orig).unwrap_or(0);
-                        *tilemap.get_tile_mut(Point { x, y }).unwrap() ^= true;
-                        *pamelit
-                            .get_tile_mut(Point { y: x, x: y })
-                            .unwrap() ^= true;
-                        let v_answer = get_h_reflection(&pamelit, v_orig)
-                            .map(|x| x * 100)
-                            .unwrap_or(0);
-                        *pamelit
-                            .get_tile_mut(Point { y: x, x: y })
-                            .unwrap() ^= true;
-                        let answer = h_answer + v_answer;
-                        if answer != 0 && answer != same_answer {
-                            different_answer = Some(answer);
-                            break 'outer;
-                        }
-                    }
+    tilemaps
+}
+
+fn summarize(tilemaps: &[BitGrid], smudges: u32) -> usize {
+    tilemaps
+        .iter()
+        .flat_map(|x| get_reflections(x, smudges))
+        .map(|x| x.mirror.summarize())
+        .sum()
+}
+
+fn main() {
+    let mut show_smudges = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--smudges" => {
+                show_smudges = Some(
+                    args.next()
+                        .expect("--smudges needs a number")
+                        .parse::<u32>()
+                        .expect("--smudges needs a number"),
+                )
+            }
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
+    let input = std::io::read_to_string(std::io::stdin()).unwrap();
+    let tilemaps = parse_patterns(&input);
+    if let Some(smudges) = show_smudges {
+        for (index, tilemap) in tilemaps.iter().enumerate() {
+            for reflection in get_reflections(tilemap, smudges) {
+                println!("Pattern {}: {:?}", index + 1, reflection.mirror);
+                for (a, b) in reflection.smudges {
+                    println!("  smudge at {a:?} or {b:?}");
                 }
-                different_answer.unwrap()
-            })
-            .sum::<usize>()
+            }
+        }
+        println!(
+            "Answer with {smudges} smudges: {}",
+            summarize(&tilemaps, smudges)
+        );
+    }
+    println!("Puzzle 1 answer: {}", summarize(&tilemaps, 0));
+    println!("Puzzle 2 answer: {}", summarize(&tilemaps, 1));
+}
+
+#[test]
+fn test_sample_smudges() {
+    let tilemaps = parse_patterns(include_str!("../../sample/13.txt"));
+    assert_eq!(summarize(&tilemaps, 0), 405);
+    assert_eq!(
+        get_reflections(&tilemaps[0], 1),
+        [Reflection {
+            mirror: Mirror::Horizontal(3),
+            smudges: vec![(Point { x: 0, y: 0 }, Point { x: 0, y: 5 })],
+        }]
+    );
+    assert_eq!(
+        get_reflections(&tilemaps[1], 1),
+        [Reflection {
+            mirror: Mirror::Horizontal(1),
+            smudges: vec![(Point { x: 4, y: 0 }, Point { x: 4, y: 1 })],
+        }]
     );
 }
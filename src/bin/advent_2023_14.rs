@@ -3,7 +3,7 @@ use std::{
     fmt::{Display, Formatter, Result as FmtResult},
 };
 
-use advent_2023::{Point, Tilemap};
+use advent_2023::{animate::Recorder, image::Rgb, Point, Tilemap};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 enum Tile {
@@ -82,7 +82,25 @@ fn spin_cycle(mut tilemap: Tilemap<Tile>) -> Tilemap<Tile> {
     tilemap
 }
 
+/// By `Tile` discriminant.
+const PALETTE: [Rgb; 3] = [[16, 16, 16], [224, 176, 64], [112, 112, 112]];
+
 fn main() {
+    let mut recorder = None;
+    let mut image_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--animate" => {
+                let path = args.next().expect("--animate needs a path");
+                recorder = Some(
+                    Recorder::new(path, &PALETTE).scale(4).max_frames(1000),
+                );
+            }
+            "--image" => image_path = args.next(),
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let mut buf = vec![];
     let mut tilemap = Tilemap::new_empty();
@@ -110,6 +128,9 @@ fn main() {
     for generation in 1..=NUM_GENERATIONS {
         cur_tilemap = spin_cycle(cur_tilemap);
         println!("Generation {generation}:\n{cur_tilemap}");
+        if let Some(recorder) = recorder.as_mut() {
+            recorder.capture(&cur_tilemap, |x| *x as u8).unwrap();
+        }
         if let Some(cycle_start_generation) =
             previous_generations.get(&cur_tilemap)
         {
@@ -124,6 +145,14 @@ fn main() {
         }
         previous_generations.insert(cur_tilemap.clone(), generation);
     }
+    if let Some(recorder) = recorder {
+        recorder.finish().unwrap();
+    }
+    if let Some(path) = image_path {
+        cur_tilemap
+            .write_image(path, |x| PALETTE[*x as usize], 4)
+            .unwrap();
+    }
     println!(
         "Puzzle 2 answer: {}",
         cur_tilemap
@@ -13,7 +13,7 @@ fn main() {
     );
     let mut buckets: Vec<Vec<(&str, u8)>> = vec![vec![]; 256];
     for instruction in line.split(',') {
-        let split_point = instruction.find(|x| x == '-' || x == '=').unwrap();
+        let split_point = instruction.find(['-', '=']).unwrap();
         let label = &instruction[..split_point];
         let length = &instruction[split_point + 1..];
         let command = instruction.as_bytes()[split_point];
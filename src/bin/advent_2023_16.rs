@@ -1,6 +1,13 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use advent_2023::{Direction, Point, Tilemap};
+use advent_2023::{
+    animate::Recorder,
+    bitgrid::BitGrid,
+    image::write_pixels,
+    render::{Cell, Color, Renderer},
+    Direction, Point, Tilemap,
+};
+use rayon::prelude::*;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Mirror {
@@ -17,6 +24,12 @@ struct Laser {
     pos: Point,
 }
 
+/// A one-direction slice that lives forever, like the splitters' do.
+fn just(dir: Direction) -> &'static [Direction] {
+    let index = dir.to_ordinal();
+    &Direction::ALL[index..=index]
+}
+
 impl Mirror {
     pub fn from_char(ch: char) -> Mirror {
         match ch {
@@ -28,29 +41,23 @@ impl Mirror {
             _ => panic!("Corrupted puzzle"),
         }
     }
+    pub fn to_char(self) -> char {
+        match self {
+            Mirror::Nothing => '.',
+            Mirror::Foreslash => '/',
+            Mirror::Backslash => '\\',
+            Mirror::Bar => '|',
+            Mirror::Dash => '-',
+        }
+    }
     pub fn bounce(
         &self,
         incoming_laser_dir: Direction,
     ) -> &'static [Direction] {
         match self {
-            Mirror::Nothing => match incoming_laser_dir {
-                Direction::North => &[Direction::North],
-                Direction::South => &[Direction::South],
-                Direction::East => &[Direction::East],
-                Direction::West => &[Direction::West],
-            },
-            Mirror::Foreslash => match incoming_laser_dir {
-                Direction::North => &[Direction::East],
-                Direction::South => &[Direction::West],
-                Direction::East => &[Direction::North],
-                Direction::West => &[Direction::South],
-            },
-            Mirror::Backslash => match incoming_laser_dir {
-                Direction::North => &[Direction::West],
-                Direction::South => &[Direction::East],
-                Direction::East => &[Direction::South],
-                Direction::West => &[Direction::North],
-            },
+            Mirror::Nothing => just(incoming_laser_dir),
+            Mirror::Foreslash => just(incoming_laser_dir.reflect_foreslash()),
+            Mirror::Backslash => just(incoming_laser_dir.reflect_backslash()),
             Mirror::Bar => match incoming_laser_dir {
                 Direction::North => &[Direction::North],
                 Direction::South => &[Direction::South],
@@ -67,13 +74,16 @@ impl Mirror {
     }
 }
 
-fn energize(
+/// Shines a beam in and follows it around, returning how many tiles got
+/// energized and every laser state the beams passed through inside the map.
+/// If there's a recorder, it gets a frame for every step the beams take.
+fn energize_with_visits(
     tilemap: &Tilemap<Mirror>,
     obrien_dir: Direction,
     obrien_pos: Point,
-) -> usize {
-    let mut heatmap: Tilemap<bool> =
-        Tilemap::new(tilemap.get_width(), tilemap.get_height());
+    mut recorder: Option<&mut Recorder>,
+) -> (usize, HashSet<Laser>) {
+    let mut heatmap = BitGrid::new(tilemap.get_width(), tilemap.get_height());
     let mut lasers = vec![Laser {
         beam_dir: obrien_dir,
         pos: obrien_pos,
@@ -83,14 +93,25 @@ fn energize(
         (tilemap.get_width() * tilemap.get_height() * 4) as usize,
     );
     while !lasers.is_empty() {
+        if let Some(recorder) = recorder.as_deref_mut() {
+            let mut frame = tilemap.map(|x| (*x != Mirror::Nothing) as u8);
+            for point in heatmap.find_tiles() {
+                frame.set_tile(point, 2);
+            }
+            for laser in lasers.iter() {
+                if frame.get_tile(laser.pos).is_some() {
+                    frame.set_tile(laser.pos, 3);
+                }
+            }
+            recorder.capture(&frame, |x| *x).unwrap();
+        }
         let mut new_lasers = vec![];
         for in_laser in lasers.iter() {
             if ever_laser.contains(in_laser) {
                 continue;
-            } else {
-                ever_laser.insert(*in_laser);
             }
             if let Some(mirror) = tilemap.get_tile(in_laser.pos) {
+                ever_laser.insert(*in_laser);
                 heatmap.set_tile(in_laser.pos, true);
                 for direction in mirror.bounce(in_laser.beam_dir) {
                     new_lasers.push(Laser {
@@ -103,10 +124,405 @@ fn energize(
         lasers.clear();
         std::mem::swap(&mut lasers, &mut new_lasers);
     }
-    heatmap.iter().filter(|x| **x).count()
+    (heatmap.count_ones() as usize, ever_laser)
+}
+
+/// Which directions the beams were travelling in when they entered each tile.
+fn beam_map(
+    tilemap: &Tilemap<Mirror>,
+    visits: &HashSet<Laser>,
+) -> Tilemap<[bool; 4]> {
+    let mut beams: Tilemap<[bool; 4]> =
+        Tilemap::new(tilemap.get_width(), tilemap.get_height());
+    for laser in visits.iter() {
+        if let Some(dirs) = beams.get_tile_mut(laser.pos) {
+            dirs[laser.beam_dir.to_ordinal()] = true;
+        }
+    }
+    beams
+}
+
+fn beam_glyph(dirs: &[bool; 4]) -> char {
+    let vertical = dirs[Direction::North.to_ordinal()]
+        || dirs[Direction::South.to_ordinal()];
+    let horizontal = dirs[Direction::East.to_ordinal()]
+        || dirs[Direction::West.to_ordinal()];
+    match dirs.iter().filter(|x| **x).count() {
+        0 => '.',
+        1 => Direction::ALL
+            .iter()
+            .find(|x| dirs[x.to_ordinal()])
+            .unwrap()
+            .to_arrow(),
+        _ if !horizontal => '↕',
+        _ if !vertical => '↔',
+        _ => '┼',
+    }
+}
+
+/// Prints the map with the beams drawn in. Mirrors stay as they are (lit up
+/// if a beam hit them), empty tiles get an arrow for the beam going through
+/// them, or a crossing glyph if more than one went through.
+fn display_beams(tilemap: &Tilemap<Mirror>, visits: &HashSet<Laser>) {
+    let beams = beam_map(tilemap, visits);
+    Renderer::new(tilemap, |point, mirror| {
+        let dirs = beams.get_tile(point).unwrap();
+        let lit = dirs.iter().any(|x| *x);
+        match (mirror, lit) {
+            (Mirror::Nothing, false) => Cell::plain('·'),
+            (Mirror::Nothing, true) => {
+                Cell::plain(beam_glyph(dirs)).fg(Color::YELLOW).bold()
+            }
+            (_, false) => Cell::plain(mirror.to_char()).fg(Color::WHITE),
+            (_, true) => Cell::plain(mirror.to_char())
+                .fg(Color::BRIGHT_WHITE)
+                .bg(Color::RED)
+                .bold(),
+        }
+    })
+    .print();
+}
+
+/// Empty, mirror, energized, and the front of a beam, for animations.
+const FRAME_PALETTE: [[u8; 3]; 4] =
+    [[16, 16, 16], [128, 128, 128], [96, 64, 16], [255, 255, 64]];
+
+/// Colors for beams travelling in each direction, by ordinal.
+const BEAM_COLORS: [[u8; 3]; 4] =
+    [[255, 64, 64], [64, 255, 64], [64, 128, 255], [255, 255, 64]];
+const MIRROR_COLOR: [u8; 3] = [128, 128, 128];
+const LIT_COLOR: [u8; 3] = [255, 255, 255];
+
+/// Writes the beams out as a picture (PNG or PPM, by extension). Each tile
+/// becomes a 3×3 block: the mirror (if any) in grey, the middle in white if
+/// energized, and an arm for each side a beam came in or went out of,
+/// colored by the direction that beam was travelling.
+fn write_beam_image(
+    path: &str,
+    tilemap: &Tilemap<Mirror>,
+    visits: &HashSet<Laser>,
+) -> std::io::Result<()> {
+    let width = tilemap.get_width() * 3;
+    let height = tilemap.get_height() * 3;
+    let mut pixels: Tilemap<[u8; 3]> = Tilemap::new(width, height);
+    for pos in tilemap.find_tiles(|x| *x != Mirror::Nothing) {
+        let center = Point {
+            x: pos.x * 3 + 1,
+            y: pos.y * 3 + 1,
+        };
+        let mirror = *tilemap.get_tile(pos).unwrap();
+        let corners: &[Point] = match mirror {
+            Mirror::Foreslash => {
+                &[Point { x: 1, y: -1 }, Point { x: -1, y: 1 }]
+            }
+            Mirror::Backslash => {
+                &[Point { x: -1, y: -1 }, Point { x: 1, y: 1 }]
+            }
+            Mirror::Bar => &[Point::NORTH, Point::SOUTH],
+            Mirror::Dash => &[Point::EAST, Point::WEST],
+            Mirror::Nothing => unreachable!(),
+        };
+        pixels.set_tile(center, MIRROR_COLOR);
+        for corner in corners.iter() {
+            pixels.set_tile(center + *corner, MIRROR_COLOR);
+        }
+    }
+    for laser in visits.iter() {
+        let center = Point {
+            x: laser.pos.x * 3 + 1,
+            y: laser.pos.y * 3 + 1,
+        };
+        let mirror = tilemap.get_tile(laser.pos).unwrap();
+        pixels.set_tile(center, LIT_COLOR);
+        let color = BEAM_COLORS[laser.beam_dir.to_ordinal()];
+        pixels.set_tile(center - laser.beam_dir, color);
+        for direction in mirror.bounce(laser.beam_dir) {
+            pixels.set_tile(
+                center + *direction,
+                BEAM_COLORS[direction.to_ordinal()],
+            );
+        }
+    }
+    write_pixels(path, &pixels)
+}
+
+/// One straight-ish run of a beam, starting from a particular laser state and
+/// following mirrors until it hits a splitter edge-on, leaves the map, or
+/// comes back around to where it started.
+struct Segment {
+    /// Every tile this segment passes through (possibly with repeats).
+    cells: Vec<Point>,
+    /// The lasers that come out of the splitter at the end, if any.
+    successors: Vec<Laser>,
+}
+
+impl Segment {
+    fn trace(tilemap: &Tilemap<Mirror>, start: Laser) -> Segment {
+        let mut cells = vec![];
+        let mut laser = start;
+        while let Some(mirror) = tilemap.get_tile(laser.pos) {
+            cells.push(laser.pos);
+            match mirror.bounce(laser.beam_dir) {
+                &[direction] => {
+                    laser = Laser {
+                        beam_dir: direction,
+                        pos: laser.pos + direction,
+                    };
+                }
+                directions => {
+                    return Segment {
+                        cells,
+                        successors: directions
+                            .iter()
+                            .map(|&direction| Laser {
+                                beam_dir: direction,
+                                pos: laser.pos + direction,
+                            })
+                            .collect(),
+                    };
+                }
+            }
+            // Without splitting, every laser state has exactly one
+            // predecessor, so the only loop we can get stuck in is one that
+            // passes back through the start.
+            if laser == start {
+                break;
+            }
+        }
+        Segment {
+            cells,
+            successors: vec![],
+        }
+    }
+}
+
+/// The mirror grid, boiled down to a graph of beam segments between
+/// splitters. Every strongly connected component of that graph energizes the
+/// same tiles no matter where in it you start, so we only work each one out
+/// once.
+struct BeamGraph {
+    /// Index of the segment that starts with a given laser.
+    nodes: HashMap<Laser, usize>,
+    /// Which strongly connected component each segment belongs to.
+    node_component: Vec<usize>,
+    /// How many tiles get energized starting from each component.
+    component_counts: Vec<usize>,
+}
+
+impl BeamGraph {
+    pub fn new(tilemap: &Tilemap<Mirror>) -> BeamGraph {
+        // A segment can only start at the edge of the map or just outside a
+        // splitter, so we know every node up front.
+        let mut starts = edge_starts(tilemap);
+        for pos in tilemap.find_tiles(|x| *x == Mirror::Bar) {
+            for direction in [Direction::North, Direction::South] {
+                starts.push(Laser {
+                    beam_dir: direction,
+                    pos: pos + direction,
+                });
+            }
+        }
+        for pos in tilemap.find_tiles(|x| *x == Mirror::Dash) {
+            for direction in [Direction::East, Direction::West] {
+                starts.push(Laser {
+                    beam_dir: direction,
+                    pos: pos + direction,
+                });
+            }
+        }
+        let mut nodes = HashMap::with_capacity(starts.len());
+        starts.retain(|laser| {
+            let index = nodes.len();
+            *nodes.entry(*laser).or_insert(index) == index
+        });
+        let segments: Vec<Segment> = starts
+            .par_iter()
+            .map(|laser| Segment::trace(tilemap, *laser))
+            .collect();
+        let edges: Vec<Vec<usize>> = segments
+            .iter()
+            .map(|segment| {
+                segment
+                    .successors
+                    .iter()
+                    .filter_map(|laser| nodes.get(laser).copied())
+                    .collect()
+            })
+            .collect();
+        let components = strongly_connected_components(&edges);
+        let mut node_component = vec![0; segments.len()];
+        for (index, component) in components.iter().enumerate() {
+            for &node in component.iter() {
+                node_component[node] = index;
+            }
+        }
+        // Each component's energized set is its own cells plus the sets of
+        // every component downstream of it. Tarjan's algorithm hands the
+        // components to us downstream-first, so by the time we get to one,
+        // everything it feeds into is already done. We throw a set away as
+        // soon as the last component that feeds into it is done with it.
+        let mut downstreams = vec![vec![]; components.len()];
+        let mut upstream_counts = vec![0usize; components.len()];
+        for (index, component) in components.iter().enumerate() {
+            let downstream = &mut downstreams[index];
+            for &node in component.iter() {
+                for &next in edges[node].iter() {
+                    let next = node_component[next];
+                    if next != index && !downstream.contains(&next) {
+                        downstream.push(next);
+                        upstream_counts[next] += 1;
+                    }
+                }
+            }
+        }
+        let words = (tilemap.get_width() as usize
+            * tilemap.get_height() as usize)
+            .div_ceil(64);
+        let mut sets: Vec<Option<Vec<u64>>> = vec![None; components.len()];
+        let mut component_counts = vec![0; components.len()];
+        for (index, component) in components.iter().enumerate() {
+            let mut set = vec![0u64; words];
+            for &node in component.iter() {
+                for cell in segments[node].cells.iter() {
+                    let bit = (cell.x + cell.y * tilemap.get_width()) as usize;
+                    set[bit / 64] |= 1 << (bit % 64);
+                }
+            }
+            for &next in downstreams[index].iter() {
+                let next_set = sets[next].as_ref().unwrap();
+                for (a, b) in set.iter_mut().zip(next_set.iter()) {
+                    *a |= *b;
+                }
+                upstream_counts[next] -= 1;
+                if upstream_counts[next] == 0 {
+                    sets[next] = None;
+                }
+            }
+            component_counts[index] =
+                set.iter().map(|x| x.count_ones() as usize).sum();
+            if upstream_counts[index] > 0 {
+                sets[index] = Some(set);
+            }
+        }
+        BeamGraph {
+            nodes,
+            node_component,
+            component_counts,
+        }
+    }
+    /// Returns the number of tiles that would be energized by a laser
+    /// starting at the given edge of the map (or just outside a splitter).
+    pub fn energize(&self, laser: Laser) -> usize {
+        let node = *self
+            .nodes
+            .get(&laser)
+            .expect("that laser doesn't start a segment");
+        self.component_counts[self.node_component[node]]
+    }
+}
+
+/// Tarjan's algorithm, done with an explicit stack so that a big enough map
+/// can't blow the real one. Components come out in reverse topological
+/// order.
+fn strongly_connected_components(edges: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let mut indices = vec![UNVISITED; edges.len()];
+    let mut lowlinks = vec![0; edges.len()];
+    let mut on_stack = vec![false; edges.len()];
+    let mut stack = vec![];
+    let mut components = vec![];
+    let mut next_index = 0;
+    for root in 0..edges.len() {
+        if indices[root] != UNVISITED {
+            continue;
+        }
+        // (node, how many of its edges we've looked at so far)
+        let mut call_stack = vec![(root, 0)];
+        while let Some(&mut (node, ref mut edge_index)) = call_stack.last_mut()
+        {
+            if *edge_index == 0 && indices[node] == UNVISITED {
+                indices[node] = next_index;
+                lowlinks[node] = next_index;
+                next_index += 1;
+                stack.push(node);
+                on_stack[node] = true;
+            }
+            if let Some(&next) = edges[node].get(*edge_index) {
+                *edge_index += 1;
+                if indices[next] == UNVISITED {
+                    call_stack.push((next, 0));
+                } else if on_stack[next] {
+                    lowlinks[node] = lowlinks[node].min(indices[next]);
+                }
+                continue;
+            }
+            call_stack.pop();
+            if let Some(&(parent, _)) = call_stack.last() {
+                lowlinks[parent] = lowlinks[parent].min(lowlinks[node]);
+            }
+            if lowlinks[node] == indices[node] {
+                let mut component = vec![];
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == node {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// Every laser that could come in from the edge of the map.
+fn edge_starts(tilemap: &Tilemap<Mirror>) -> Vec<Laser> {
+    let width = tilemap.get_width();
+    let height = tilemap.get_height();
+    let north_iter = (0..width).map(|x| Laser {
+        beam_dir: Direction::North,
+        pos: Point { x, y: height - 1 },
+    });
+    let south_iter = (0..width).map(|x| Laser {
+        beam_dir: Direction::South,
+        pos: Point { x, y: 0 },
+    });
+    let east_iter = (0..height).map(|y| Laser {
+        beam_dir: Direction::East,
+        pos: Point { x: 0, y },
+    });
+    let west_iter = (0..height).map(|y| Laser {
+        beam_dir: Direction::West,
+        pos: Point { x: width - 1, y },
+    });
+    north_iter
+        .chain(south_iter)
+        .chain(east_iter)
+        .chain(west_iter)
+        .collect()
 }
 
 fn main() {
+    let mut show_beams = false;
+    let mut beam_image = None;
+    let mut recorder = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--show-beams" => show_beams = true,
+            "--beam-image" => {
+                beam_image =
+                    Some(args.next().expect("--beam-image needs a path"))
+            }
+            "--animate" => {
+                let path = args.next().expect("--animate needs a path");
+                recorder = Some(Recorder::new(path, &FRAME_PALETTE).scale(4));
+            }
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let mut buf = vec![];
     let mut tilemap = Tilemap::new_empty();
@@ -115,40 +531,112 @@ fn main() {
         buf.extend(line.chars().map(Mirror::from_char));
         tilemap.add_row(&buf);
     }
-    println!(
-        "Puzzle 1 solution: {}",
-        energize(&tilemap, Direction::East, Point { x: 0, y: 0 })
+    let (count, visits) = energize_with_visits(
+        &tilemap,
+        Direction::East,
+        Point { x: 0, y: 0 },
+        recorder.as_mut(),
     );
-    let north_iter = (0..tilemap.get_width()).map(|x| {
-        (
-            Direction::North,
-            Point {
-                x,
-                y: tilemap.get_height() - 1,
-            },
-        )
-    });
-    let south_iter = (0..tilemap.get_width())
-        .map(|x| (Direction::South, Point { x, y: 0 }));
-    let east_iter = (0..tilemap.get_height())
-        .map(|y| (Direction::East, Point { x: 0, y }));
-    let west_iter = (0..tilemap.get_height()).map(|y| {
-        (
-            Direction::East,
-            Point {
-                x: tilemap.get_width() - 1,
-                y,
-            },
-        )
-    });
+    if let Some(recorder) = recorder {
+        recorder.finish().unwrap();
+    }
+    if show_beams {
+        display_beams(&tilemap, &visits);
+    }
+    if let Some(path) = beam_image {
+        write_beam_image(&path, &tilemap, &visits).unwrap();
+    }
+    println!("Puzzle 1 solution: {count}");
+    let graph = BeamGraph::new(&tilemap);
     println!(
         "Puzzle 2 solution: {}",
-        north_iter
-            .chain(south_iter.chain(east_iter.chain(west_iter)))
-            .map(|(start_dir, start_pos)| {
-                energize(&tilemap, start_dir, start_pos)
-            })
+        edge_starts(&tilemap)
+            .into_par_iter()
+            .map(|laser| graph.energize(laser))
             .max()
             .unwrap_or(0)
     )
 }
+
+#[cfg(test)]
+fn sample_tilemap() -> Tilemap<Mirror> {
+    let mut tilemap = Tilemap::new_empty();
+    for line in include_str!("../../sample/16.txt").lines() {
+        let row: Vec<Mirror> = line.chars().map(Mirror::from_char).collect();
+        tilemap.add_row(&row);
+    }
+    tilemap
+}
+
+#[test]
+fn test_graph_matches_simulation() {
+    let tilemap = sample_tilemap();
+    let graph = BeamGraph::new(&tilemap);
+    for laser in edge_starts(&tilemap) {
+        assert_eq!(
+            graph.energize(laser),
+            energize_with_visits(&tilemap, laser.beam_dir, laser.pos, None).0,
+            "{laser:?}"
+        );
+    }
+}
+
+#[test]
+fn test_sample_part2() {
+    let tilemap = sample_tilemap();
+    let graph = BeamGraph::new(&tilemap);
+    assert_eq!(
+        edge_starts(&tilemap)
+            .into_iter()
+            .map(|laser| graph.energize(laser))
+            .max(),
+        Some(51)
+    );
+}
+
+#[test]
+fn test_sample_beams() {
+    let tilemap = sample_tilemap();
+    let (count, visits) = energize_with_visits(
+        &tilemap,
+        Direction::East,
+        Point { x: 0, y: 0 },
+        None,
+    );
+    assert_eq!(count, 46);
+    let beams = beam_map(&tilemap, &visits);
+    let drawn: Vec<String> = beams
+        .rows()
+        .zip(tilemap.rows())
+        .map(|(beam_row, mirror_row)| {
+            beam_row
+                .iter()
+                .zip(mirror_row.iter())
+                .map(|(dirs, mirror)| match mirror {
+                    Mirror::Nothing => beam_glyph(dirs),
+                    _ => mirror.to_char(),
+                })
+                .collect()
+        })
+        .collect();
+    // The same as the puzzle's picture, but with ┼ where it says 2.
+    assert_eq!(
+        drawn,
+        [
+            "→|←←←\\....",
+            "|↓-.\\↑....",
+            ".↓...|-→→→",
+            ".↓...↓↑.|.",
+            ".↓...↓↑...",
+            ".↓...↓↑..\\",
+            ".↓../┼\\\\..",
+            "←-→-/↓↓|..",
+            ".|←←←┼-|.\\",
+            ".↓//.|.↓..",
+        ]
+    );
+    // One beam going down and one going left cross here.
+    let crossing = beams.get_tile(Point { x: 5, y: 8 }).unwrap();
+    assert!(crossing[Direction::South.to_ordinal()]);
+    assert!(crossing[Direction::West.to_ordinal()]);
+}
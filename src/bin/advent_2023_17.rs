@@ -4,20 +4,103 @@ use std::{
     rc::Rc,
 };
 
-use advent_2023::{Direction, Point, Tilemap};
+use advent_2023::{
+    animate::Recorder,
+    image::Rgb,
+    render::{Cell, Color, Renderer},
+    Direction8, Point, Tilemap,
+};
+
+/// How the crucible is allowed to move. The puzzle's rules are what you get
+/// from `MoveRules::crucible`; everything else is for playing around.
+#[derive(Debug, Clone)]
+struct MoveRules {
+    start: Point,
+    goal: Point,
+    min_straights: u8,
+    max_straights: u8,
+    /// Whether the crucible may turn right around.
+    allow_reverse: bool,
+    /// Extra heat lost every time the crucible changes direction.
+    turn_penalty: u32,
+    /// Whether the crucible may also move diagonally.
+    diagonals: bool,
+}
+
+impl MoveRules {
+    pub fn crucible(
+        pricemap: &Tilemap<u8>,
+        min_straights: u8,
+        max_straights: u8,
+    ) -> MoveRules {
+        MoveRules {
+            start: Point { x: 0, y: 0 },
+            goal: Point {
+                x: pricemap.get_width() - 1,
+                y: pricemap.get_height() - 1,
+            },
+            min_straights,
+            max_straights,
+            allow_reverse: false,
+            turn_penalty: 0,
+            diagonals: false,
+        }
+    }
+    pub fn steps(&self) -> &'static [Point] {
+        const STEPS: &[Point] = &[
+            Point::NORTH,
+            Point::SOUTH,
+            Point::EAST,
+            Point::WEST,
+            Point { x: 1, y: -1 },
+            Point { x: 1, y: 1 },
+            Point { x: -1, y: 1 },
+            Point { x: -1, y: -1 },
+        ];
+        if self.diagonals {
+            STEPS
+        } else {
+            &STEPS[..4]
+        }
+    }
+}
+
+fn step_arrow(step: Point) -> char {
+    Direction8::try_from(step).unwrap().to_arrow()
+}
 
 #[derive(Debug, Clone)]
 struct PathNode {
     parent: Option<Rc<PathNode>>,
     pos: Point,
     heatloss: u32,
-    entry_dir: Option<Direction>,
+    /// Heat lost to turn penalties (not included in `heatloss`).
+    penalty: u32,
+    turns: u32,
+    entry_dir: Option<Point>,
     dir_count: u8,
 }
 
+impl PathNode {
+    pub fn total_cost(&self) -> u32 {
+        self.heatloss + self.penalty
+    }
+    /// Every node along the path, from the start to here.
+    pub fn route(&self) -> Vec<&PathNode> {
+        let mut ret = vec![];
+        let mut next_node = Some(self);
+        while let Some(node) = next_node {
+            ret.push(node);
+            next_node = node.parent.as_ref().map(|x| x.as_ref());
+        }
+        ret.reverse();
+        ret
+    }
+}
+
 impl PartialEq for PathNode {
     fn eq(&self, other: &Self) -> bool {
-        self.heatloss == other.heatloss
+        self.total_cost() == other.total_cost()
     }
 }
 
@@ -29,49 +112,81 @@ impl PartialOrd for PathNode {
 
 impl Ord for PathNode {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.heatloss.cmp(&other.heatloss).reverse()
+        self.total_cost().cmp(&other.total_cost()).reverse()
     }
 }
 
 impl Eq for PathNode {}
 
-#[allow(unused)]
-fn display_path(end_node: &PathNode, width: i32, height: i32) {
-    let mut tiles = Tilemap::new_with('·', width, height);
-    let mut next_node = Some(end_node);
-    while let Some(node) = next_node {
-        tiles.set_tile(
-            node.pos,
-            node.entry_dir.map(|x| x.to_arrow()).unwrap_or('?'),
-        );
-        next_node = node.parent.as_ref().map(|x| x.as_ref());
-    }
-    print!("{}", tiles);
+fn display_path(end_node: &PathNode, pricemap: &Tilemap<u8>) {
+    let route = end_node.route();
+    Renderer::new(pricemap, |_, _| Cell::plain('·'))
+        .path(
+            route.iter().map(|node| {
+                (node.pos, node.entry_dir.map(step_arrow).unwrap_or('?'))
+            }),
+            Color::YELLOW,
+        )
+        .print();
+}
+
+fn display_cost(end_node: &PathNode) {
+    println!(
+        "{} steps, {} turns, {} heat lost to tiles + {} to turning = {}",
+        end_node.route().len() - 1,
+        end_node.turns,
+        end_node.heatloss,
+        end_node.penalty,
+        end_node.total_cost()
+    );
 }
 
-fn find_path(
+#[cfg(test)]
+fn find_path(pricemap: &Tilemap<u8>, rules: &MoveRules) -> Option<PathNode> {
+    find_path_recorded(pricemap, rules, None)
+}
+
+/// Unexplored, explored, on the frontier, and on the final path.
+const FRAME_PALETTE: [Rgb; 4] =
+    [[16, 16, 16], [64, 64, 160], [255, 224, 64], [255, 64, 64]];
+
+/// Like `find_path`, but if there's a recorder it gets to watch the search
+/// spread out from the start, and then the path it found.
+fn find_path_recorded(
     pricemap: &Tilemap<u8>,
-    min_straights: u8,
-    max_straights: u8,
-) -> PathNode {
+    rules: &MoveRules,
+    mut recorder: Option<&mut Recorder>,
+) -> Option<PathNode> {
+    let mut frame: Tilemap<u8> =
+        Tilemap::new(pricemap.get_width(), pricemap.get_height());
     let mut live_nodes: BinaryHeap<PathNode> = vec![PathNode {
         parent: None,
-        pos: Point { x: 0, y: 0 },
+        pos: rules.start,
         heatloss: 0,
+        penalty: 0,
+        turns: 0,
         entry_dir: None,
         dir_count: 0,
     }]
     .into();
-    let mut dead_nodes: HashSet<(Point, Direction, u8)> =
+    let mut dead_nodes: HashSet<(Point, Point, u8)> =
         HashSet::with_capacity(1048576);
     while let Some(path_node) = live_nodes.pop() {
-        if path_node.pos
-            == (Point {
-                x: pricemap.get_width() - 1,
-                y: pricemap.get_height() - 1,
-            })
+        if let Some(recorder) = recorder.as_deref_mut() {
+            frame.set_tile(path_node.pos, 1);
+            recorder.capture(&frame, |x| *x).unwrap();
+        }
+        if path_node.pos == rules.goal
+            && (path_node.entry_dir.is_none()
+                || path_node.dir_count >= rules.min_straights)
         {
-            return path_node;
+            if let Some(recorder) = recorder {
+                for node in path_node.route() {
+                    frame.set_tile(node.pos, 3);
+                }
+                recorder.capture_now(&frame, |x| *x).unwrap();
+            }
+            return Some(path_node);
         }
         let デスノード = path_node
             .entry_dir
@@ -83,14 +198,14 @@ fn find_path(
             dead_nodes.insert(デスノード);
         }
         let path_node = Rc::new(path_node);
-        for dir in Direction::ALL {
+        for dir in rules.steps() {
             let dir = *dir;
-            if Some(-dir) == path_node.entry_dir
+            if (!rules.allow_reverse && Some(-dir) == path_node.entry_dir)
                 || (Some(dir) == path_node.entry_dir
-                    && path_node.dir_count >= max_straights)
+                    && path_node.dir_count >= rules.max_straights)
                 || (path_node.entry_dir.is_some()
                     && Some(dir) != path_node.entry_dir
-                    && path_node.dir_count < min_straights)
+                    && path_node.dir_count < rules.min_straights)
             {
                 // no going too far forwards
                 continue;
@@ -99,10 +214,14 @@ fn find_path(
             let Some(&price) = pricemap.get_tile(dest_pos) else {
                 continue;
             };
+            let is_turn = path_node.entry_dir.is_some_and(|x| x != dir);
             let next_node = PathNode {
                 parent: Some(path_node.clone()),
                 pos: dest_pos,
                 heatloss: path_node.heatloss + price as u32,
+                penalty: path_node.penalty
+                    + if is_turn { rules.turn_penalty } else { 0 },
+                turns: path_node.turns + is_turn as u32,
                 entry_dir: Some(dir),
                 dir_count: if path_node.entry_dir == Some(dir) {
                     path_node.dir_count + 1
@@ -110,13 +229,46 @@ fn find_path(
                     1
                 },
             };
+            if frame.get_tile(next_node.pos) == Some(&0) {
+                frame.set_tile(next_node.pos, 2);
+            }
             live_nodes.push(next_node);
         }
     }
-    unreachable!()
+    None
+}
+
+fn parse_point(s: &str) -> Point {
+    let (x, y) = s.split_once(',').expect("points look like X,Y");
+    Point {
+        x: x.parse().unwrap(),
+        y: y.parse().unwrap(),
+    }
 }
 
 fn main() {
+    let mut show_path = false;
+    let mut animation_path = None;
+    let mut start = None;
+    let mut goal = None;
+    let mut allow_reverse = false;
+    let mut turn_penalty = 0;
+    let mut diagonals = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--show-path" => show_path = true,
+            "--start" => start = Some(parse_point(&args.next().unwrap())),
+            "--goal" => goal = Some(parse_point(&args.next().unwrap())),
+            "--reverse" => allow_reverse = true,
+            "--turn-penalty" => {
+                turn_penalty = args.next().unwrap().parse().unwrap()
+            }
+            "--diagonal" => diagonals = true,
+            "--animate" => animation_path = args.next(),
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let mut buf = vec![];
     let mut pricemap = Tilemap::new_empty();
@@ -125,6 +277,84 @@ fn main() {
         buf.extend(line.chars().map(|x| x.to_digit(10).unwrap() as u8));
         pricemap.add_row(&buf);
     }
-    println!("Puzzle 1 answer: {}", find_path(&pricemap, 1, 3).heatloss);
-    println!("Puzzle 2 answer: {}", find_path(&pricemap, 4, 10).heatloss);
+    // Both puzzles go in the same animation, one after the other.
+    let mut recorder = animation_path.map(|path| {
+        let tiles = pricemap.get_width() * pricemap.get_height();
+        Recorder::new(path, &FRAME_PALETTE)
+            .scale(4)
+            .every((tiles / 20).max(1) as usize)
+            .max_frames(2000)
+    });
+    for (puzzle, min_straights, max_straights) in [(1, 1, 3), (2, 4, 10)] {
+        let mut rules =
+            MoveRules::crucible(&pricemap, min_straights, max_straights);
+        rules.start = start.unwrap_or(rules.start);
+        rules.goal = goal.unwrap_or(rules.goal);
+        rules.allow_reverse = allow_reverse;
+        rules.turn_penalty = turn_penalty;
+        rules.diagonals = diagonals;
+        let Some(path) =
+            find_path_recorded(&pricemap, &rules, recorder.as_mut())
+        else {
+            println!("Puzzle {puzzle}: the goal can't be reached!");
+            continue;
+        };
+        if show_path {
+            display_path(&path, &pricemap);
+            display_cost(&path);
+        }
+        println!("Puzzle {puzzle} answer: {}", path.total_cost());
+    }
+    if let Some(recorder) = recorder {
+        recorder.finish().unwrap();
+    }
+}
+
+#[cfg(test)]
+fn sample_pricemap() -> Tilemap<u8> {
+    let mut pricemap = Tilemap::new_empty();
+    for line in include_str!("../../sample/17.txt").lines() {
+        let row: Vec<u8> = line
+            .chars()
+            .map(|x| x.to_digit(10).unwrap() as u8)
+            .collect();
+        pricemap.add_row(&row);
+    }
+    pricemap
+}
+
+#[test]
+fn test_sample() {
+    let pricemap = sample_pricemap();
+    let part1 = find_path(&pricemap, &MoveRules::crucible(&pricemap, 1, 3));
+    assert_eq!(part1.map(|x| x.total_cost()), Some(102));
+    let part2 = find_path(&pricemap, &MoveRules::crucible(&pricemap, 4, 10));
+    assert_eq!(part2.map(|x| x.total_cost()), Some(94));
+}
+
+#[test]
+fn test_ultra_crucible_must_stop_straight() {
+    // From the puzzle text: the ultra crucible can't just turn into the
+    // corner at the end.
+    let mut pricemap = Tilemap::new_empty();
+    pricemap.add_row(&[1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1]);
+    pricemap.add_row(&[9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1]);
+    pricemap.add_row(&[9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1]);
+    pricemap.add_row(&[9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1]);
+    pricemap.add_row(&[9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 9, 1]);
+    let path = find_path(&pricemap, &MoveRules::crucible(&pricemap, 4, 10));
+    assert_eq!(path.map(|x| x.total_cost()), Some(71));
+}
+
+#[test]
+fn test_turn_penalty_and_breakdown() {
+    let pricemap = sample_pricemap();
+    let mut rules = MoveRules::crucible(&pricemap, 1, 3);
+    rules.turn_penalty = 100;
+    let path = find_path(&pricemap, &rules).unwrap();
+    assert_eq!(path.total_cost(), path.heatloss + path.penalty);
+    assert_eq!(path.penalty, path.turns * 100);
+    let route = path.route();
+    assert_eq!(route.first().unwrap().pos, rules.start);
+    assert_eq!(route.last().unwrap().pos, rules.goal);
 }
@@ -1,6 +1,9 @@
-use std::cmp::Ordering;
-
-use advent_2023::{Direction, Point, Tilemap};
+use advent_2023::{
+    grid::GrowingTilemap,
+    polygon::LatticePolygon,
+    render::{Cell, Color, Renderer},
+    Direction, Point, Tilemap,
+};
 
 #[derive(Debug)]
 struct Command {
@@ -13,136 +16,143 @@ struct Command {
 struct Command2 {
     dir: Direction,
     count: i32,
-}
-
-#[derive(Debug)]
-struct LineSegment {
-    start: Point,
-    end: Point,
+    color: u32,
 }
 
 const NO_PIXEL: u32 = u32::MAX;
 const FILL_PIXEL: u32 = 0xFF00FFu32;
 
 fn display_map(tilemap: &Tilemap<u32>, dirmap: &Tilemap<Option<Direction>>) {
-    for (color_rows, dir_rows) in tilemap.rows().zip(dirmap.rows()) {
-        for (pix, dir) in color_rows.iter().copied().zip(dir_rows.iter()) {
-            if pix == NO_PIXEL {
-                print!("\x1B[0m·");
-            } else {
-                let r = (pix >> 16) & 255;
-                let g = (pix >> 8) & 255;
-                let b = pix & 255;
-                print!(
-                    "\x1B[48;2;{r};{g};{b}m{}",
-                    dir.map(|x| x.to_arrow()).unwrap_or('?')
-                );
-            }
+    Renderer::new(tilemap, |point, pix| {
+        if *pix == NO_PIXEL {
+            Cell::plain('·')
+        } else {
+            let dir = dirmap.get_tile(point).unwrap();
+            Cell::plain(dir.map(|x| x.to_arrow()).unwrap_or('?'))
+                .bg(Color::Rgb(pix.to_be_bytes()[1..].try_into().unwrap()))
         }
-        println!("\x1B[0m");
-    }
+    })
+    .print();
 }
 
-#[derive(Debug)]
-struct Crossing {
-    is_upward: bool,
-    is_downward: bool,
-    x_coord: i32,
-}
-fn part2_method(
-    line_segments: &[LineSegment],
-    min_x: i32,
-    width: usize,
-) -> u64 {
-    let mut crossings: Vec<Crossing> = Vec::with_capacity(15);
-    let mut y_coordinates: Vec<i32> = line_segments
-        .iter()
-        .filter(|segment| segment.start.y != segment.end.y)
-        .flat_map(|segment| {
-            [
-                segment.start.y.min(segment.end.y) - 1,
-                segment.start.y.min(segment.end.y),
-                segment.start.y.min(segment.end.y) + 1,
-                segment.start.y.max(segment.end.y) - 1,
-                segment.start.y.max(segment.end.y),
-                segment.start.y.max(segment.end.y) + 1,
-            ]
-            .into_iter()
+/// Where the digger ends up after each command, starting from the origin.
+fn vertices(
+    commands: impl Iterator<Item = (Direction, i32)>,
+) -> Vec<Point<i64>> {
+    let mut pos = Point { x: 0, y: 0 };
+    commands
+        .map(|(dir, count)| {
+            pos = pos + dir * count as i64;
+            pos
         })
-        .collect();
-    y_coordinates.sort();
-    // remove duplicates...
-    for i in (0..y_coordinates.len() - 1).rev() {
-        if y_coordinates[i] == y_coordinates[i + 1] {
-            y_coordinates.remove(i + 1);
-        }
-    }
-    // remove the out-of-bounds ones at the start and end
-    y_coordinates.pop();
-    y_coordinates.remove(0);
-    let mut previous_y = y_coordinates[0];
-    let mut previous_area = 0;
-    let mut total = 0;
-    let mut buf = vec![false; width];
-    for y in y_coordinates.iter().copied() {
-        total += previous_area * (y - previous_y) as u64;
-        previous_y = y;
+        .collect()
+}
+
+/// Paints the dig plan, each trench in its own color and the lagoon in
+/// `FILL_PIXEL`. If the plan is bigger than `max_size` tiles in either
+/// dimension, each pixel covers a square of several tiles, sampled from the
+/// middle (trenches always win).
+fn plan_image(
+    segments: &[(Direction, i32, u32)],
+    max_size: u32,
+) -> Tilemap<u32> {
+    assert!(max_size >= 1, "The image needs at least one pixel");
+    let max_size = max_size as i64;
+    let corners =
+        vertices(segments.iter().map(|&(dir, count, _)| (dir, count)));
+    let min_x = corners.iter().map(|x| x.x).min().unwrap_or(0);
+    let max_x = corners.iter().map(|x| x.x).max().unwrap_or(0);
+    let min_y = corners.iter().map(|x| x.y).min().unwrap_or(0);
+    let max_y = corners.iter().map(|x| x.y).max().unwrap_or(0);
+    let scale =
+        ((max_x - min_x + 1).max(max_y - min_y + 1) + max_size - 1) / max_size;
+    let width = (max_x - min_x) / scale + 1;
+    let height = (max_y - min_y) / scale + 1;
+    let mut pixels =
+        Tilemap::new_with(0x202020u32, width as i32, height as i32);
+    // Fill: count the vertical trenches to the right of the middle of each
+    // pixel.
+    let mut crossings = vec![];
+    for (y, row) in pixels.rows_mut().enumerate() {
+        let world_y = min_y + y as i64 * scale + scale / 2;
         crossings.clear();
-        crossings.extend(
-            line_segments
-                .iter()
-                .filter(|segment| segment.start.y != segment.end.y)
-                .filter(|segment| {
-                    (segment.start.y.min(segment.end.y)
-                        ..=segment.start.y.max(segment.end.y))
-                        .contains(&y)
-                })
-                .map(|segment| Crossing {
-                    is_upward: segment.start.y > segment.end.y,
-                    is_downward: segment.start.y < segment.end.y,
-                    x_coord: segment.start.x,
-                }),
-        );
-        crossings.sort_by(|a, b| a.x_coord.cmp(&b.x_coord));
-        assert!(crossings[0].is_upward); // assert clockwise winding
-        for i in (0..crossings.len() - 1).rev() {
-            // kill all but leftmost of consecutive ups
-            if crossings[i].is_upward && crossings[i + 1].is_upward {
-                crossings.remove(i + 1);
-            }
-            // kill all but rightmost of consecutive downs
-            if crossings[i].is_downward && crossings[i + 1].is_downward {
-                crossings.remove(i);
+        let mut prev = *corners.last().unwrap();
+        for &next in corners.iter() {
+            if prev.x == next.x
+                && (prev.y.min(next.y)..prev.y.max(next.y)).contains(&world_y)
+            {
+                crossings.push(prev.x);
             }
+            prev = next;
         }
-        if cfg!(debug_assertions) {
-            for q in crossings.chunks(2) {
-                assert_eq!(q.len(), 2);
-                assert!(q[0].is_upward);
-                assert!(q[1].is_downward);
-            }
-        }
-        buf[..].fill(false);
+        crossings.sort();
         for pair in crossings.chunks_exact(2) {
-            buf[(pair[0].x_coord - min_x) as usize
-                ..=(pair[1].x_coord - min_x) as usize]
-                .fill(true);
+            let left = (pair[0] - min_x + scale / 2) / scale;
+            let right = (pair[1] - min_x - scale / 2) / scale;
+            if left <= right {
+                row[left as usize..=right as usize].fill(FILL_PIXEL);
+            }
         }
-        for segment in line_segments.iter().filter(|segment| {
-            segment.start.y == segment.end.y && segment.start.y == y
-        }) {
-            buf[(segment.start.x.min(segment.end.x) - min_x) as usize
-                ..=(segment.start.x.max(segment.end.x) - min_x) as usize]
-                .fill(true);
+    }
+    // Trenches
+    let mut pos = *corners.last().unwrap();
+    for (&(_, _, color), &next) in segments.iter().zip(corners.iter()) {
+        let start = Point {
+            x: ((pos.x.min(next.x) - min_x) / scale) as i32,
+            y: ((pos.y.min(next.y) - min_y) / scale) as i32,
+        };
+        let end = Point {
+            x: ((pos.x.max(next.x) - min_x) / scale) as i32,
+            y: ((pos.y.max(next.y) - min_y) / scale) as i32,
+        };
+        for y in start.y..=end.y {
+            for x in start.x..=end.x {
+                pixels.set_tile(Point { x, y }, color);
+            }
         }
-        previous_area = buf.iter().filter(|x| **x).count() as u64;
+        pos = next;
+    }
+    pixels
+}
+
+/// Writes `plan_image` out as a picture, PNG or PPM depending on the
+/// extension of `path`.
+fn write_plan_image(
+    path: &str,
+    segments: &[(Direction, i32, u32)],
+    max_size: u32,
+) -> std::io::Result<()> {
+    if max_size == 0 {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            "the image needs at least one pixel",
+        ));
     }
-    assert_eq!(previous_y, *y_coordinates.last().unwrap());
-    total += previous_area;
-    total
+    plan_image(segments, max_size).write_image(
+        path,
+        |pix| pix.to_be_bytes()[1..].try_into().unwrap(),
+        1,
+    )
 }
 
 fn main() {
+    let mut image_paths = [None, None];
+    let mut image_size = 1024;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--image-part1" => image_paths[0] = args.next(),
+            "--image-part2" => image_paths[1] = args.next(),
+            "--image-size" => {
+                image_size = args
+                    .next()
+                    .and_then(|x| x.parse::<u32>().ok())
+                    .filter(|x| *x >= 1)
+                    .expect("--image-size needs a positive number")
+            }
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let commands: Vec<Command> = lines
         .map(|line| {
@@ -150,13 +160,7 @@ fn main() {
             let &[dir, count, color] = &splat[..] else {
                 panic!()
             };
-            let dir = match dir {
-                "U" => Direction::North,
-                "D" => Direction::South,
-                "R" => Direction::East,
-                "L" => Direction::West,
-                x => panic!("Unknown direction {x:?}!"),
-            };
+            let dir: Direction = dir.parse().unwrap();
             let count = count.parse().unwrap();
             let color =
                 color.strip_prefix("(#").unwrap().strip_suffix(")").unwrap();
@@ -164,32 +168,9 @@ fn main() {
             Command { dir, count, color }
         })
         .collect();
-    // Find extents
-    let mut min_x = i32::MAX;
-    let mut max_x = i32::MIN;
-    let mut min_y = i32::MAX;
-    let mut max_y = i32::MIN;
+    let mut colormap = GrowingTilemap::new(NO_PIXEL);
+    let mut dirmap = GrowingTilemap::new(None);
     let mut pos = Point { x: 0, y: 0 };
-    for command in commands.iter() {
-        pos = pos + command.dir * command.count;
-        min_x = min_x.min(pos.x);
-        max_x = max_x.max(pos.x);
-        min_y = min_y.min(pos.y);
-        max_y = max_y.max(pos.y);
-    }
-    println!("X ranges from {min_x}..={max_x}");
-    println!("Y ranges from {min_y}..={max_y}");
-    let start_x = -min_x;
-    let start_y = -min_y;
-    let width = (max_x - min_x) + 1;
-    let height = (max_y - min_y) + 1;
-    println!("Map is {width}x{height}, start at {start_x},{start_y}");
-    let mut colormap = Tilemap::new_with(NO_PIXEL, width, height);
-    let mut dirmap = Tilemap::new_with(None, width, height);
-    let mut pos = Point {
-        x: start_x,
-        y: start_y,
-    };
     for command in commands.iter() {
         for _ in 0..command.count {
             pos = pos + command.dir;
@@ -197,6 +178,15 @@ fn main() {
             dirmap.set_tile(pos, Some(command.dir));
         }
     }
+    let (start, mut colormap) = colormap.into_tilemap();
+    let (_, mut dirmap) = dirmap.into_tilemap();
+    println!(
+        "Map is {}x{}, start at {},{}",
+        colormap.get_width(),
+        colormap.get_height(),
+        -start.x,
+        -start.y
+    );
     println!("Lines:");
     display_map(&colormap, &dirmap);
     // Fill!
@@ -232,26 +222,24 @@ fn main() {
         "Okay, here we go. Puzzle 1: {}",
         colormap.iter().filter(|x| **x != NO_PIXEL).count()
     );
-    // Test part 2 calculation on part 1...
-    let mut pos = Point { x: 0, y: 0 };
-    let line_segments: Vec<LineSegment> = commands
-        .iter()
-        .map(|command| {
-            let start = pos;
-            pos = pos + command.dir * command.count;
-            let end = pos;
-            LineSegment { start, end }
-        })
-        .collect();
-    println!(
-        "Part 1 answer, part 2 method: {}",
-        part2_method(&line_segments, min_x, width as usize)
+    let lagoon = LatticePolygon::new(
+        vertices(commands.iter().map(|command| (command.dir, command.count)))
+            .into_iter()
+            .map(|corner| (corner.x, corner.y)),
     );
+    println!("Part 1 answer, part 2 method: {}", lagoon.total_points());
+    if let Some(path) = &image_paths[0] {
+        let segments: Vec<(Direction, i32, u32)> = commands
+            .iter()
+            .map(|command| (command.dir, command.count, command.color))
+            .collect();
+        write_plan_image(path, &segments, image_size).unwrap();
+    }
     // The REAL puzzle!
     let commands: Vec<Command2> = commands
         .iter()
         .map(|command| {
-            let dir = match (command.color & 15) {
+            let dir = match command.color & 15 {
                 0 => Direction::East,
                 1 => Direction::South,
                 2 => Direction::West,
@@ -259,43 +247,45 @@ fn main() {
                 _ => unreachable!(),
             };
             let count = (command.color >> 4) as i32;
-            Command2 { dir, count }
-        })
-        .collect();
-    // Reduce, reuse, re---HEY!
-    let mut min_x = i32::MAX;
-    let mut max_x = i32::MIN;
-    let mut min_y = i32::MAX;
-    let mut max_y = i32::MIN;
-    let mut pos = Point { x: 0, y: 0 };
-    for command in commands.iter() {
-        pos = pos + command.dir * command.count;
-        min_x = min_x.min(pos.x);
-        max_x = max_x.max(pos.x);
-        min_y = min_y.min(pos.y);
-        max_y = max_y.max(pos.y);
-    }
-    println!("X ranges from {min_x}..={max_x}");
-    println!("Y ranges from {min_y}..={max_y}");
-    let start_x = -min_x;
-    let start_y = -min_y;
-    let width = (max_x - min_x) + 1;
-    let height = (max_y - min_y) + 1;
-    println!(
-        "Map would be {width}x{height}, would start at {start_x},{start_y}"
-    );
-    let mut pos = Point { x: 0, y: 0 };
-    let line_segments: Vec<LineSegment> = commands
-        .iter()
-        .map(|command| {
-            let start = pos;
-            pos = pos + command.dir * command.count;
-            let end = pos;
-            LineSegment { start, end }
+            Command2 {
+                dir,
+                count,
+                color: command.color,
+            }
         })
         .collect();
-    println!(
-        "Part 2 answer: {}",
-        part2_method(&line_segments, min_x, width as usize)
+    let lagoon = LatticePolygon::new(
+        vertices(commands.iter().map(|command| (command.dir, command.count)))
+            .into_iter()
+            .map(|corner| (corner.x, corner.y)),
     );
+    println!("Part 2 answer: {}", lagoon.total_points());
+    if let Some(path) = &image_paths[1] {
+        let segments: Vec<(Direction, i32, u32)> = commands
+            .iter()
+            .map(|command| (command.dir, command.count, command.color))
+            .collect();
+        write_plan_image(path, &segments, image_size).unwrap();
+    }
+}
+
+#[test]
+fn test_plan_image_size() {
+    let segments: Vec<(Direction, i32, u32)> =
+        include_str!("../../sample/18.txt")
+            .lines()
+            .map(|line| {
+                let splat: Vec<&str> = line.split(' ').collect();
+                (splat[0].parse().unwrap(), splat[1].parse().unwrap(), 0)
+            })
+            .collect();
+    // The sample is 7x10 tiles.
+    let full = plan_image(&segments, 1024);
+    assert_eq!((full.get_width(), full.get_height()), (7, 10));
+    // Squeezed down to 4 pixels tall, each pixel is 3x3 tiles.
+    let small = plan_image(&segments, 4);
+    assert_eq!((small.get_width(), small.get_height()), (3, 4));
+    let tiny = plan_image(&segments, 1);
+    assert_eq!((tiny.get_width(), tiny.get_height()), (1, 1));
+    assert!(write_plan_image("/dev/null", &segments, 0).is_err());
 }
@@ -1,15 +1,38 @@
-use advent_2023::{Direction, Point, Tilemap};
+use advent_2023::{
+    animate::Recorder,
+    bitgrid::BitGrid,
+    image::Rgb,
+    render::{Cell, Renderer},
+    Point, Tilemap,
+};
 
 fn display_map(map: &Tilemap<bool>) {
-    for row in map.rows() {
-        for el in row.iter() {
-            print!("{}", if *el { '◘' } else { '•' });
-        }
-        println!();
-    }
+    Renderer::new(map, |_, el| Cell::plain(if *el { '◘' } else { '•' }))
+        .print();
 }
 
+const ROCK_COLOR: Rgb = [96, 96, 96];
+const PLOT_COLOR: Rgb = [24, 24, 24];
+const REACHED_COLOR: Rgb = [64, 255, 64];
+
+/// Plot, rock, reached (on an even step), and the wavefront, for animations.
+const FRAME_PALETTE: [Rgb; 4] =
+    [PLOT_COLOR, ROCK_COLOR, REACHED_COLOR, [255; 3]];
+
 fn main() {
+    let mut image_path = None;
+    let mut recorder = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--image" => image_path = args.next(),
+            "--animate" => {
+                let path = args.next().expect("--animate needs a path");
+                recorder = Some(Recorder::new(path, &FRAME_PALETTE).scale(4));
+            }
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let mut buf = vec![];
     let mut rockmap = Tilemap::new_empty();
@@ -28,50 +51,46 @@ fn main() {
         rockmap.add_row(&buf);
     }
     let elf_point = elf_point.unwrap();
-    let mut active_steps = Vec::with_capacity(
-        (rockmap.get_width() * rockmap.get_height()) as usize,
-    );
-    let mut next_steps = Vec::with_capacity(
-        (rockmap.get_width() * rockmap.get_height()) as usize,
-    );
-    active_steps.push(elf_point);
-    let mut reachmap =
-        Tilemap::new_with(false, rockmap.get_width(), rockmap.get_height());
-    reachmap.set_tile(elf_point, true);
+    let rockmap = BitGrid::from(&rockmap);
+    let open = !&rockmap;
+    // Everywhere the elf could be after each step. Every plot one step
+    // away from one of them is where it could be next.
+    let mut positions =
+        BitGrid::new(rockmap.get_width(), rockmap.get_height());
+    positions.set_tile(elf_point, true);
+    // Only the animation needs to remember the earlier even steps.
+    let mut reachmap = recorder.as_ref().map(|_| positions.clone());
     for step in 1..=64 {
-        assert!(next_steps.is_empty());
-        for start_point in active_steps.drain(..) {
-            for next_step in
-                Direction::ALL.iter().copied().map(|dir| start_point + dir)
-            {
-                let Some(false) = rockmap.get_tile(next_step) else {
-                    continue;
-                };
-                if step % 2 == 0 {
-                    let Some(false) = reachmap.get_tile(next_step) else {
-                        continue;
-                    };
-                    reachmap.set_tile(next_step, true);
-                } else if !Direction::ALL
-                    .iter()
-                    .copied()
-                    .map(|dir| next_step + dir)
-                    .any(|つぎのつぎの| {
-                        Some(&false) == rockmap.get_tile(つぎのつぎの)
-                            && Some(&false) == reachmap.get_tile(つぎのつぎの)
-                    })
-                {
-                    continue;
-                }
-                next_steps.push(next_step);
+        if let (Some(recorder), Some(reachmap)) =
+            (recorder.as_mut(), reachmap.as_ref())
+        {
+            let mut frame = rockmap.to_tilemap().map(|x| *x as u8);
+            for point in reachmap.find_tiles() {
+                frame.set_tile(point, 2);
+            }
+            for point in positions.find_tiles() {
+                frame.set_tile(point, 3);
             }
+            recorder.capture(&frame, |x| *x).unwrap();
+        }
+        positions = &positions.neighbors() & &open;
+        if let Some(reachmap) = reachmap.as_mut().filter(|_| step % 2 == 0) {
+            *reachmap = &*reachmap | &positions;
+        }
+    }
+    if let Some(recorder) = recorder {
+        recorder.finish().unwrap();
+    }
+    display_map(&positions.to_tilemap());
+    if let Some(path) = image_path {
+        let mut picture =
+            rockmap
+                .to_tilemap()
+                .map(|x| if *x { ROCK_COLOR } else { PLOT_COLOR });
+        for point in positions.find_tiles() {
+            picture.set_tile(point, REACHED_COLOR);
         }
-        std::mem::swap(&mut active_steps, &mut next_steps);
-        next_steps.clear();
+        picture.write_image(path, |x| *x, 4).unwrap();
     }
-    display_map(&reachmap);
-    println!(
-        "Puzzle 1 answer: {}",
-        reachmap.iter().filter(|x| **x).count()
-    );
+    println!("Puzzle 1 answer: {}", positions.count_ones());
 }
@@ -1,6 +1,13 @@
-use advent_2023::{Direction, Point, Tilemap};
+use std::collections::{BTreeMap, VecDeque};
+
+use advent_2023::{
+    golly::Pattern,
+    tiling::{Sectors, Tiling},
+    Direction, Point, Tilemap,
+};
 
 const ELF_TARGET: u64 = 26501365;
+const UNREACHABLE: u32 = u32::MAX;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -54,89 +61,441 @@ fn run_cells(prevmap: &Tilemap<Cell>, nextmap: &mut Tilemap<Cell>) {
     }
 }
 
-fn get_populations<const Q: usize, const QQ: usize>(
+/// How many reachable and unreachable plots there are in each sector.
+type Populations = Sectors<(u64, u64)>;
+
+fn get_populations(
+    tiling: &Tiling,
     cellmap: &Tilemap<Cell>,
-) -> [(u64, u64); QQ] {
-    let mut ret = [(0, 0); QQ];
-    for (y, row) in cellmap.rows().enumerate() {
-        let y_sector = (y * Q) / cellmap.get_height() as usize;
-        for (x, cell) in row.iter().copied().enumerate() {
-            let x_sector = (x * Q) / cellmap.get_width() as usize;
-            if cell == Cell::Reachable {
-                let sector = x_sector + y_sector * Q;
-                ret[sector].0 += 1;
-            } else if cell == Cell::Unreachable {
-                let sector = x_sector + y_sector * Q;
-                ret[sector].1 += 1;
-            }
-        }
-    }
-    ret
+    bigmap: &Tilemap<Cell>,
+) -> Populations {
+    tiling.tally(
+        bigmap,
+        cellmap.get_width(),
+        cellmap.get_height(),
+        |pop: &mut (u64, u64), cell| match cell {
+            Cell::Reachable => pop.0 += 1,
+            Cell::Unreachable => pop.1 += 1,
+            _ => (),
+        },
+    )
 }
 
+/// Works out how many plots are reachable after `target` steps, given how
+/// the tiling's populations grew over every generation until it filled up.
+///
+/// Sectors inside the tiling are counted as they are. Past the edge, a copy
+/// of the garden looks just like the nearest sector on the edge did some
+/// whole number of garden-widths' worth of generations ago. Copies straight
+/// out from an axis or edge sector are one per step outward, copies out
+/// from a corner sector are one more per step.
 fn count_plots(
-    populations: &[[(u64, u64); 25]],
+    populations: &[Populations],
     cellmap: &Tilemap<Cell>,
     target: u64,
 ) -> u64 {
+    let tiling = populations[0].get_tiling();
+    // With only the middle sector, there's nothing to extrapolate from.
+    assert!(
+        tiling.get_radius() > 0,
+        "Counting plots needs at least a 3×3 tiling"
+    );
+    let pick = |gen: u64, sector: Point, flip: bool| {
+        let gen = gen.min((populations.len() - 1) as u64) as usize;
+        let pop = &populations[gen][sector];
+        if flip {
+            pop.1
+        } else {
+            pop.0
+        }
+    };
     let mut total = populations
         [target.min((populations.len() - 1) as u64) as usize]
         .iter()
-        .map(|(a, b)| if target & 1 == 0 { a } else { b })
+        .map(|(_, (a, b))| if target & 1 == 0 { a } else { b })
         .sum();
-    // Add orthogonals
+    let mut outer_sectors = tiling.axis_sectors();
+    outer_sectors.extend(tiling.edge_sectors());
     for steppu in 1.. {
         let step = cellmap.get_width() as u64 * steppu;
         let Some(gen) = target.checked_sub(step) else {
             break;
         };
-        let gen = gen.min((populations.len() - 1) as u64) as usize;
-        for sector in [2, 10, 14, 22] {
-            let pop = &populations[gen][sector];
-            if (steppu & 1) ^ (target & 1) == 0 {
-                total += pop.0;
-            } else {
-                total += pop.1;
-            }
+        let flip = (steppu & 1) ^ (target & 1) != 0;
+        for sector in outer_sectors.iter() {
+            total += pick(gen, *sector, flip);
+        }
+        let multiplier = steppu + 1;
+        for sector in tiling.corner_sectors() {
+            total += pick(gen, sector, flip) * multiplier;
         }
     }
-    // Add orthodiagonals
-    for steppu in 1.. {
-        let step = cellmap.get_width() as u64 * steppu;
-        let Some(gen) = target.checked_sub(step) else {
-            break;
+    total
+}
+
+/// How many plots there are, really, after `generation` generations.
+fn count_cells(cellmap: &Tilemap<Cell>, generation: u64) -> u64 {
+    let wanted = if generation & 1 == 0 {
+        Cell::Reachable
+    } else {
+        Cell::Unreachable
+    };
+    cellmap.iter().filter(|x| **x == wanted).count() as u64
+}
+
+/// How many steps it takes the elf to get to every plot in a block of
+/// (2×`radius`+1)² copies of the garden, centered on the original.
+struct DistanceTable {
+    radius: i32,
+    width: i32,
+    height: i32,
+    distances: Tilemap<u32>,
+}
+
+impl DistanceTable {
+    pub fn new(
+        cellmap: &Tilemap<Cell>,
+        elf_point: Point,
+        radius: i32,
+    ) -> DistanceTable {
+        let width = cellmap.get_width();
+        let height = cellmap.get_height();
+        let mut distances = Tilemap::new_with(
+            UNREACHABLE,
+            width * (radius * 2 + 1),
+            height * (radius * 2 + 1),
+        );
+        let start = Point {
+            x: elf_point.x + width * radius,
+            y: elf_point.y + height * radius,
         };
-        let gen = gen.min((populations.len() - 1) as u64) as usize;
-        for sector in [1, 3, 5, 15, 9, 19, 21, 23] {
-            let pop = &populations[gen][sector];
-            if (steppu & 1) ^ (target & 1) == 0 {
-                total += pop.0;
-            } else {
-                total += pop.1;
+        distances.set_tile(start, 0);
+        let mut queue = VecDeque::from([start]);
+        while let Some(pos) = queue.pop_front() {
+            let next_distance = distances.get_tile(pos).unwrap() + 1;
+            for next in Direction::ALL.iter().copied().map(|dir| pos + dir) {
+                let Some(&UNREACHABLE) = distances.get_tile(next) else {
+                    continue;
+                };
+                let cell = Point {
+                    x: next.x % width,
+                    y: next.y % height,
+                };
+                if *cellmap.get_tile(cell).unwrap() == Cell::Rock {
+                    continue;
+                }
+                distances.set_tile(next, next_distance);
+                queue.push_back(next);
             }
         }
+        DistanceTable {
+            radius,
+            width,
+            height,
+            distances,
+        }
     }
-    // Add superdiagonals
-    for steppu in 1.. {
-        let step = cellmap.get_width() as u64 * steppu;
-        let Some(gen) = target.checked_sub(step) else {
-            break;
+    /// All the distances in the copy of the garden at the given tile offset.
+    pub fn tile(&self, tile: Point) -> impl '_ + Iterator<Item = u32> {
+        let left = ((tile.x + self.radius) * self.width) as usize;
+        let top = tile.y + self.radius;
+        (top * self.height..(top + 1) * self.height).flat_map(move |y| {
+            self.distances.get_row(y).unwrap()
+                [left..left + self.width as usize]
+                .iter()
+                .copied()
+        })
+    }
+    /// Checks that, from ring `ring` outward, every further copy of the
+    /// garden is just the one before it plus a whole garden's width (or
+    /// height) of steps. That's what the fast path depends on. This needs
+    /// a table at least three tiles wider than `ring`, so that the edge of
+    /// the table can't throw off the distances we're checking.
+    pub fn settles_at(&self, ring: i32) -> bool {
+        assert!(self.radius >= ring + 3);
+        let shifted = |base: Point, step: Point, k: i32, amount: u32| {
+            self.tile(base).zip(self.tile(base + step * k)).all(
+                |(near, far)| {
+                    if near == UNREACHABLE || far == UNREACHABLE {
+                        near == far
+                    } else {
+                        far == near + amount * k as u32
+                    }
+                },
+            )
         };
-        let multiplier = steppu + 1;
-        let gen = gen.min((populations.len() - 1) as u64) as usize;
-        for sector in [0, 4, 20, 24] {
-            let pop = &populations[gen][sector];
-            if (steppu & 1) ^ (target & 1) == 0 {
-                total += pop.0 * multiplier;
-            } else {
-                total += pop.1 * multiplier;
+        for k in 1..=2 {
+            for other in -ring..=ring {
+                let strips = [
+                    (Point { x: ring, y: other }, Point::EAST, self.width),
+                    (Point { x: -ring, y: other }, Point::WEST, self.width),
+                    (Point { x: other, y: ring }, Point::SOUTH, self.height),
+                    (Point { x: other, y: -ring }, Point::NORTH, self.height),
+                ];
+                for (base, step, amount) in strips {
+                    if !shifted(base, step, k, amount as u32) {
+                        return false;
+                    }
+                }
+            }
+            for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+                let corner = Point {
+                    x: ring * sx,
+                    y: ring * sy,
+                };
+                for (step, amount) in [
+                    (Point { x: sx, y: 0 }, self.width),
+                    (Point { x: 0, y: sy }, self.height),
+                ] {
+                    if !shifted(corner, step, k, amount as u32) {
+                        return false;
+                    }
+                }
+            }
+        }
+        true
+    }
+}
+
+/// How many of `base`, `base + step`, `base + 2×step`... are at most
+/// `target` and have the same parity as it?
+fn count_line(base: u64, step: u64, target: u64) -> u128 {
+    if base > target {
+        return 0;
+    }
+    let max_k = ((target - base) / step) as u128;
+    if step.is_multiple_of(2) {
+        if base % 2 == target % 2 {
+            max_k + 1
+        } else {
+            0
+        }
+    } else {
+        let parity = ((target - base) % 2) as u128;
+        if max_k < parity {
+            0
+        } else {
+            (max_k - parity) / 2 + 1
+        }
+    }
+}
+
+/// `Σ floor((a×i + b) / m)` for `i` in `0..n`, without going through every
+/// `i`. This is the usual trick: take out the whole multiples of `m` from
+/// `a` and `b`, which leave arithmetic series behind, and then what's left
+/// is the same kind of sum with the roles of `a` and `m` swapped, so it
+/// shrinks like Euclid's algorithm.
+fn floor_sum(mut n: u128, mut m: u128, mut a: u128, mut b: u128) -> u128 {
+    let mut ret = 0;
+    loop {
+        if a >= m {
+            ret += n * (n - 1) / 2 * (a / m);
+            a %= m;
+        }
+        if b >= m {
+            ret += n * (b / m);
+            b %= m;
+        }
+        let y_max = a * n + b;
+        if y_max < m {
+            return ret;
+        }
+        (n, b, m, a) = (y_max / m, y_max % m, a, m);
+    }
+}
+
+/// How many pairs `i, j ≥ 0` have `base + i×di + j×dj` at most `target`,
+/// and with the same parity as it? That's every copy of one tile out in a
+/// corner, `i` garden widths and `j` garden heights further away.
+fn count_corner(base: u64, di: u64, dj: u64, target: u64) -> u128 {
+    let mut ret = 0;
+    // Split `i` and `j` by parity, so that each half of them moves in steps
+    // of `2×di` and `2×dj`, which can't change the parity of the sum.
+    for p in 0..2 {
+        for q in 0..2 {
+            let start = base + p * di + q * dj;
+            if start > target || start % 2 != target % 2 {
+                continue;
             }
+            let room = (target - start) as u128;
+            let (a, b) = (2 * di as u128, 2 * dj as u128);
+            // For each `i`, there are `room - a×i` steps left, which is
+            // room for `floor((room - a×i) / b) + 1` values of `j`. Going
+            // from the last `i` back to the first, that's a `floor_sum`.
+            let count_i = room / a + 1;
+            ret += floor_sum(count_i, b, a, room % a) + count_i;
+        }
+    }
+    ret
+}
+
+/// Counts the plots the elf can end on after exactly `target` steps in the
+/// infinite garden, given a table whose distances settle at `ring`.
+///
+/// Copies within `ring` of the middle are counted directly. Past that, a
+/// copy straight out along an axis is a copy on the ring plus some whole
+/// number of garden widths, and a copy off in a corner is the corner copy
+/// of the ring plus some widths and some heights.
+fn count_with_table(table: &DistanceTable, ring: i32, target: u64) -> u128 {
+    let width = table.width as u64;
+    let height = table.height as u64;
+    let reached = |distance: u32| {
+        distance != UNREACHABLE
+            && distance as u64 <= target
+            && distance as u64 % 2 == target % 2
+    };
+    let mut total = 0;
+    for y in -ring..=ring {
+        for x in -ring..=ring {
+            total += table
+                .tile(Point { x, y })
+                .filter(|distance| reached(*distance))
+                .count() as u128;
+        }
+    }
+    for other in -ring..=ring {
+        let strips = [
+            (Point { x: ring, y: other }, width),
+            (Point { x: -ring, y: other }, width),
+            (Point { x: other, y: ring }, height),
+            (Point { x: other, y: -ring }, height),
+        ];
+        for (base, step) in strips {
+            total += table
+                .tile(base)
+                .filter(|distance| *distance != UNREACHABLE)
+                .map(|distance| {
+                    count_line(distance as u64 + step, step, target)
+                })
+                .sum::<u128>();
+        }
+    }
+    for (sx, sy) in [(1, 1), (1, -1), (-1, 1), (-1, -1)] {
+        let mut distance_counts: BTreeMap<u64, u128> = BTreeMap::new();
+        for distance in table.tile(Point {
+            x: ring * sx,
+            y: ring * sy,
+        }) {
+            if distance != UNREACHABLE {
+                *distance_counts.entry(distance as u64).or_default() += 1;
+            }
+        }
+        for (distance, count) in distance_counts {
+            total += count
+                * count_corner(
+                    distance + width + height,
+                    width,
+                    height,
+                    target,
+                );
         }
     }
     total
 }
 
-fn main() {
+/// The most distances `count_by_simulation` will keep track of. At four
+/// bytes each, that's a quarter of a gigabyte.
+const MAX_SIMULATED_PLOTS: u64 = 1 << 26;
+
+/// Counts by brute force: a table big enough to hold every plot the elf
+/// could possibly get to. That's way too big for the real puzzle, so if it
+/// would be more than `MAX_SIMULATED_PLOTS`, this gives up and returns
+/// `None`.
+fn count_by_simulation(
+    cellmap: &Tilemap<Cell>,
+    elf_point: Point,
+    target: u64,
+) -> Option<u64> {
+    let smaller = cellmap.get_width().min(cellmap.get_height()) as u64;
+    let radius = (target / smaller).saturating_add(1);
+    let side = radius.saturating_mul(2).saturating_add(1);
+    let plots = side
+        .saturating_mul(cellmap.get_width() as u64)
+        .saturating_mul(side)
+        .saturating_mul(cellmap.get_height() as u64);
+    if plots > MAX_SIMULATED_PLOTS {
+        return None;
+    }
+    let table = DistanceTable::new(cellmap, elf_point, radius as i32);
+    let count = table
+        .distances
+        .iter()
+        .filter(|distance| {
+            **distance != UNREACHABLE
+                && **distance as u64 <= target
+                && **distance as u64 % 2 == target % 2
+        })
+        .count() as u64;
+    Some(count)
+}
+
+/// Counts the plots the elf can end on after exactly `target` steps in the
+/// infinite garden, by the fast path if the garden plays along and by brute
+/// force if it doesn't. The count grows like `target²`, which would be
+/// too big for a `u64` by about five billion steps.
+fn count_reachable(
+    cellmap: &Tilemap<Cell>,
+    elf_point: Point,
+    target: u64,
+) -> u128 {
+    const MAX_RING: i32 = 6;
+    let table = DistanceTable::new(cellmap, elf_point, MAX_RING + 3);
+    for ring in 1..=MAX_RING {
+        if table.settles_at(ring) {
+            return count_with_table(&table, ring, target);
+        }
+    }
+    eprintln!("Garden never settled down, simulating the hard way...");
+    count_by_simulation(cellmap, elf_point, target)
+        .expect("...and it's too big to simulate, too. Sorry!")
+        .into()
+}
+
+/// Turns a pattern from `advent_2023_21_to_golly` (or Golly, after running
+/// it) back into cells.
+fn cells_from_pattern(pattern: &Pattern) -> Tilemap<Cell> {
+    assert_eq!(pattern.rule, "Advent-2023-21", "That's not our rule!");
+    pattern.cells.map(|state| match state {
+        0 => Cell::Unknown,
+        1 => Cell::Rock,
+        2 => Cell::Reachable,
+        3 => Cell::Unreachable,
+        _ => panic!("State {state} isn't in our rule!"),
+    })
+}
+
+/// Loads a pattern that Golly has been running, and checks that `run_cells`
+/// gets the same result after the same number of generations.
+fn compare_with_golly(cellmap: &Tilemap<Cell>, elf_point: Point, path: &str) {
+    let pattern: Pattern =
+        std::fs::read_to_string(path).unwrap().parse().unwrap();
+    let golly_map = cells_from_pattern(&pattern);
+    let generation = pattern
+        .generation
+        .expect("Pattern doesn't say what generation it's at");
+    assert_eq!(golly_map.get_width() % cellmap.get_width(), 0);
+    let tiling = Tiling::new(golly_map.get_width() / cellmap.get_width());
+    let mut bigmap = tiling.tile(cellmap);
+    bigmap.set_tile(tiling.to_middle(cellmap, elf_point), Cell::Reachable);
+    assert_eq!(bigmap.get_height(), golly_map.get_height());
+    let mut altmap = bigmap.clone();
+    for _ in 0..generation {
+        run_cells(&bigmap, &mut altmap);
+        std::mem::swap(&mut bigmap, &mut altmap);
+    }
+    let mismatches = bigmap
+        .iter()
+        .zip(golly_map.iter())
+        .filter(|(a, b)| a != b)
+        .count();
+    if mismatches == 0 {
+        println!("Golly agrees with us at generation {generation}.");
+    } else {
+        println!("{mismatches} cells differ at generation {generation}!");
+    }
+}
+
+fn read_garden() -> (Tilemap<Cell>, Point) {
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let mut buf = vec![];
     let mut cellmap = Tilemap::new_empty();
@@ -159,51 +518,115 @@ fn main() {
         }));
         cellmap.add_row(&buf);
     }
+    (cellmap, elf_point.expect("Where is the elf?"))
+}
+
+fn main() {
+    let mut target = ELF_TARGET;
+    let mut simulate = false;
+    let mut sectors = false;
+    let mut factor = 5;
+    let mut verify = None;
+    let mut compare_rle = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--simulate" => simulate = true,
+            "--sectors" => sectors = true,
+            "--tiling" => {
+                factor = args
+                    .next()
+                    .and_then(|x| x.parse().ok())
+                    .filter(|x| *x >= 3 && x % 2 == 1)
+                    .expect("--tiling needs an odd number, at least 3")
+            }
+            "--verify" => verify = Some(args.next().unwrap().parse().unwrap()),
+            "--compare-rle" => compare_rle = args.next(),
+            _ => target = arg.parse().expect("Target should be a step count"),
+        }
+    }
+    let (cellmap, elf_point) = read_garden();
+    if let Some(path) = compare_rle {
+        compare_with_golly(&cellmap, elf_point, &path);
+        return;
+    }
+    if sectors || verify.is_some() {
+        let tiling = Tiling::new(factor);
+        let populations = sector_populations(&tiling, &cellmap, elf_point);
+        if let Some(limit) = verify {
+            verify_sectors(&populations, &cellmap, elf_point, limit);
+        }
+        println!(
+            "And the part 2 puzzle output is: {}",
+            count_plots(&populations, &cellmap, target)
+        );
+        return;
+    }
+    let answer = if simulate {
+        count_by_simulation(&cellmap, elf_point, target)
+            .unwrap_or_else(|| {
+                panic!(
+                    "{target} steps is too many to simulate; \
+                     try again without --simulate"
+                )
+            })
+            .into()
+    } else {
+        count_reachable(&cellmap, elf_point, target)
+    };
+    println!("Plots reachable in exactly {target} steps: {answer}");
+}
+
+/// The method I actually got my star with: grow the wavefront in a block of
+/// copies of the garden until it fills up, and remember how full each sector
+/// was along the way. Only works on square gardens with the elf right in the
+/// middle, and with the wavefront behaving itself.
+fn sector_populations(
+    tiling: &Tiling,
+    cellmap: &Tilemap<Cell>,
+    elf_point: Point,
+) -> Vec<Populations> {
     assert_eq!(cellmap.get_width() % 2, 1);
     assert_eq!(cellmap.get_height(), cellmap.get_width());
-    let elf_point = elf_point.unwrap();
     assert_eq!(elf_point.x, cellmap.get_width() / 2);
     assert_eq!(elf_point.y, cellmap.get_height() / 2);
-    let mut bigmap = Tilemap::new_empty();
-    for _ in 0..5 {
-        for row in cellmap.rows() {
-            buf.clear();
-            buf.extend(row);
-            buf.extend(row);
-            buf.extend(row);
-            buf.extend(row);
-            buf.extend(row);
-            bigmap.add_row(&buf);
-        }
-    }
-    bigmap.set_tile(
-        Point {
-            x: elf_point.x + cellmap.get_width() * 2,
-            y: elf_point.y + cellmap.get_width() * 2,
-        },
-        Cell::Reachable,
-    );
+    let mut bigmap = tiling.tile(cellmap);
+    bigmap.set_tile(tiling.to_middle(cellmap, elf_point), Cell::Reachable);
     let mut populations = vec![];
-    populations.push(get_populations::<5, 25>(&bigmap));
+    populations.push(get_populations(tiling, cellmap, &bigmap));
     let mut altmap = bigmap.clone();
     loop {
         run_cells(&bigmap, &mut altmap);
-        populations.push(get_populations::<5, 25>(&altmap));
+        populations.push(get_populations(tiling, cellmap, &altmap));
         if bigmap == altmap {
             break;
         }
         std::mem::swap(&mut bigmap, &mut altmap);
     }
+    if tiling.get_radius() == 0 {
+        return populations;
+    }
     // Stats time!
-    let orthogonal_quadrants = [7, 11, 13, 17].map(|sector| {
+    let arrival = |sector: Point| {
         populations.iter().position(|x| x[sector].0 > 0).unwrap()
-    });
+    };
+    let orthogonal_quadrants = [
+        Point { x: 0, y: -1 },
+        Point { x: -1, y: 0 },
+        Point { x: 1, y: 0 },
+        Point { x: 0, y: 1 },
+    ]
+    .map(arrival);
     println!(
         "Wavefront reaches orthogonal quadrants in: {orthogonal_quadrants:?}"
     );
-    let diagonal_quadrants = [6, 8, 16, 18].map(|sector| {
-        populations.iter().position(|x| x[sector].0 > 0).unwrap()
-    });
+    let diagonal_quadrants = [
+        Point { x: -1, y: -1 },
+        Point { x: 1, y: -1 },
+        Point { x: -1, y: 1 },
+        Point { x: 1, y: 1 },
+    ]
+    .map(arrival);
     println!(
         "Wavefront reaches diagonal quadrants in: {diagonal_quadrants:?}"
     );
@@ -213,76 +636,205 @@ fn main() {
     assert!(diagonal_quadrants
         .iter()
         .all(|x| *x == (cellmap.get_width() + 1) as usize));
-    if false {
-        drop(bigmap);
-        let mut hugemap = Tilemap::new_empty();
-        for _ in 0..15 {
-            for row in cellmap.rows() {
-                buf.clear();
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                buf.extend(row);
-                hugemap.add_row(&buf);
-            }
-        }
-        hugemap.set_tile(
-            Point {
-                x: elf_point.x + cellmap.get_width() * 7,
-                y: elf_point.y + cellmap.get_width() * 7,
-            },
-            Cell::Reachable,
-        );
-        let mut altmap = hugemap.clone();
-        let mut generation_count = 0;
-        loop {
-            run_cells(&hugemap, &mut altmap);
-            if hugemap == altmap {
-                break;
-            }
+    populations
+}
+
+/// Runs the wavefront for real, on a tiling big enough that it never hits
+/// the edge, and checks the extrapolation against it every generation up to
+/// `limit`.
+fn verify_sectors(
+    populations: &[Populations],
+    cellmap: &Tilemap<Cell>,
+    elf_point: Point,
+    limit: u64,
+) {
+    let radius = limit / cellmap.get_width() as u64 + 1;
+    let tiling = Tiling::new(radius as i32 * 2 + 1);
+    println!(
+        "Verifying up to {limit} steps on a {0}×{0} tiling...",
+        tiling.get_factor()
+    );
+    let mut hugemap = tiling.tile(cellmap);
+    hugemap.set_tile(tiling.to_middle(cellmap, elf_point), Cell::Reachable);
+    let mut altmap = hugemap.clone();
+    let mut mismatches = 0;
+    for generation in 0..=limit {
+        let expected = count_cells(&hugemap, generation);
+        let extrapolated = count_plots(populations, cellmap, generation);
+        if extrapolated != expected {
             println!(
-                "{generation_count} {}",
-                generation_count / (cellmap.get_width() as u64)
+                "Generation {generation}: extrapolated {extrapolated}, \
+                 but there are really {expected}"
             );
-            generation_count += 1;
-            if generation_count == 982 {
-                let true_populations = get_populations::<9, 81>(&hugemap);
-                for row in true_populations.chunks(9) {
-                    for col in row.iter().copied() {
-                        print!("{}\t", col.0);
-                    }
-                    println!();
+            mismatches += 1;
+        }
+        run_cells(&hugemap, &mut altmap);
+        std::mem::swap(&mut hugemap, &mut altmap);
+    }
+    if mismatches == 0 {
+        println!("All {} generations match.", limit + 1);
+    } else {
+        println!("{mismatches} generations didn't match!");
+    }
+}
+
+#[cfg(test)]
+fn sample_garden() -> (Tilemap<Cell>, Point) {
+    let mut cellmap = Tilemap::new_empty();
+    let mut elf_point = None;
+    for (y, line) in include_str!("../../sample/21.txt").lines().enumerate() {
+        let row: Vec<Cell> = line
+            .char_indices()
+            .map(|(x, char)| match char {
+                '#' => Cell::Rock,
+                'S' => {
+                    elf_point = Some(Point {
+                        x: x as i32,
+                        y: y as i32,
+                    });
+                    Cell::Unknown
+                }
+                _ => Cell::Unknown,
+            })
+            .collect();
+        cellmap.add_row(&row);
+    }
+    (cellmap, elf_point.unwrap())
+}
+
+#[test]
+fn test_sample_targets() {
+    let (cellmap, elf_point) = sample_garden();
+    for (target, expected) in [
+        (6, 16),
+        (10, 50),
+        (50, 1594),
+        (100, 6536),
+        (500, 167004),
+        (1000, 668697),
+        (5000, 16733044),
+    ] {
+        assert_eq!(count_reachable(&cellmap, elf_point, target), expected);
+    }
+    // Way past where a u64 runs out, and the corners still add up without
+    // visiting each copy of the garden.
+    let huge = count_reachable(&cellmap, elf_point, 1_000_000_000_000);
+    assert!(huge > u64::MAX as u128);
+}
+
+#[test]
+fn test_count_corner() {
+    let brute_force = |base: u64, di: u64, dj: u64, target: u64| {
+        let mut ret = 0;
+        for i in 0..=target {
+            for j in 0..=target {
+                let at = base + i * di + j * dj;
+                if at <= target && at % 2 == target % 2 {
+                    ret += 1;
                 }
             }
-            if (generation_count & 1) == 0 {
-                assert_eq!(
-                    count_plots(&populations, &cellmap, generation_count),
-                    altmap.iter().filter(|x| **x == Cell::Reachable).count()
-                        as u64
-                );
-            } else {
+        }
+        ret
+    };
+    for (di, dj) in [(11, 11), (7, 5), (4, 6), (2, 9), (1, 1)] {
+        for base in [0, 3, 12] {
+            for target in [0, 1, 17, 40, 41] {
                 assert_eq!(
-                    count_plots(&populations, &cellmap, generation_count),
-                    altmap.iter().filter(|x| **x == Cell::Unreachable).count()
-                        as u64
+                    count_corner(base, di, dj, target),
+                    brute_force(base, di, dj, target),
+                    "{base} + i×{di} + j×{dj} ≤ {target}"
                 );
             }
-            std::mem::swap(&mut hugemap, &mut altmap);
         }
     }
-    println!(
-        "And the part 2 puzzle output is: {}",
-        count_plots(&populations, &cellmap, ELF_TARGET)
-    );
+}
+
+#[test]
+fn test_fast_path_matches_simulation_off_center() {
+    // A lopsided garden with the elf nowhere near the middle.
+    let mut cellmap = Tilemap::new_empty();
+    for line in ["......#", "..#....", ".....#.", "#......", "...#..."] {
+        let row: Vec<Cell> = line
+            .chars()
+            .map(|x| if x == '#' { Cell::Rock } else { Cell::Unknown })
+            .collect();
+        cellmap.add_row(&row);
+    }
+    let elf_point = Point { x: 1, y: 3 };
+    for target in [0, 1, 7, 30, 101, 250] {
+        assert_eq!(
+            count_reachable(&cellmap, elf_point, target),
+            count_by_simulation(&cellmap, elf_point, target)
+                .unwrap()
+                .into(),
+            "{target}"
+        );
+    }
+    assert_eq!(count_by_simulation(&cellmap, elf_point, ELF_TARGET), None);
+}
+
+#[test]
+fn test_sectors_any_tiling() {
+    // Square, elf in the middle, clear lines out from the elf and around the
+    // edge, like the real input.
+    let mut cellmap = Tilemap::new_empty();
+    for line in [
+        "...........",
+        ".#..#...#..",
+        "..#.....#..",
+        "...#.......",
+        ".#.......#.",
+        "...........",
+        "..#.....#..",
+        "....#...#..",
+        ".#.......#.",
+        "...#..#....",
+        "...........",
+    ] {
+        let row: Vec<Cell> = line
+            .chars()
+            .map(|x| if x == '#' { Cell::Rock } else { Cell::Unknown })
+            .collect();
+        cellmap.add_row(&row);
+    }
+    let elf_point = Point { x: 5, y: 5 };
+    for factor in [3, 5, 7] {
+        let populations =
+            sector_populations(&Tiling::new(factor), &cellmap, elf_point);
+        for target in [0, 5, 64, 137, 1000] {
+            assert_eq!(
+                count_plots(&populations, &cellmap, target) as u128,
+                count_reachable(&cellmap, elf_point, target),
+                "{factor}×{factor}, {target} steps"
+            );
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "at least a 3×3 tiling")]
+fn test_single_sector_tiling() {
+    let (cellmap, elf_point) = sample_garden();
+    let populations = sector_populations(&Tiling::new(1), &cellmap, elf_point);
+    count_plots(&populations, &cellmap, 64);
+}
+
+#[test]
+fn test_pattern_round_trip() {
+    let (cellmap, elf_point) = sample_garden();
+    let tiling = Tiling::new(3);
+    let mut bigmap = tiling.tile(&cellmap);
+    bigmap.set_tile(tiling.to_middle(&cellmap, elf_point), Cell::Reachable);
+    let mut altmap = bigmap.clone();
+    for _ in 0..7 {
+        run_cells(&bigmap, &mut altmap);
+        std::mem::swap(&mut bigmap, &mut altmap);
+    }
+    let pattern = Pattern {
+        rule: "Advent-2023-21".to_string(),
+        generation: Some(7),
+        cells: bigmap.map(|x| *x as u8),
+    };
+    let reloaded: Pattern = pattern.to_string().parse().unwrap();
+    assert_eq!(cells_from_pattern(&reloaded), bigmap);
 }
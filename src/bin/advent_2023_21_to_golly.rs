@@ -1,6 +1,38 @@
 // I tried to chicken out and use golly for this, but it was too big for golly.
+// It's still nice for watching smaller tilings, and for checking
+// `advent_2023_21_part2` against (see its `--compare-rle` option). Golly
+// needs the rule too, so `--rule Advent-2023-21.rule` writes that out.
 
-use advent_2023::{Point, Tilemap};
+use advent_2023::{golly::Pattern, tiling::Tiling, Point, Tilemap};
+
+const RULE_NAME: &str = "Advent-2023-21";
+
+/// State 0 is a plot nobody's reached yet, 1 is rock, 2 is a plot reached in
+/// an even number of steps, and 3 is one reached in an odd number. Same as
+/// `Cell` in `advent_2023_21_part2`.
+const RULE_TABLE: &str = "\
+@RULE Advent-2023-21
+
+Advent of Code 2023, day 21. The elf's possible positions spread out one
+step per generation, alternating between even and odd.
+
+@TABLE
+n_states:4
+neighborhood:vonNeumann
+symmetries:permute
+var a={0,1,2,3}
+var b={0,1,2,3}
+var c={0,1,2,3}
+# C,N,E,S,W,C'
+0,2,a,b,c,3
+0,3,a,b,c,2
+
+@COLORS
+0 16 16 16
+1 128 128 128
+2 64 255 64
+3 64 128 255
+";
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[repr(u8)]
@@ -10,6 +42,16 @@ enum Cell {
 }
 
 fn main() {
+    let mut factor = 1;
+    let mut rule_path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tile" => factor = args.next().unwrap().parse().unwrap(),
+            "--rule" => rule_path = args.next(),
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let mut buf = vec![];
     let mut cellmap = Tilemap::new_empty();
@@ -32,31 +74,20 @@ fn main() {
         }));
         cellmap.add_row(&buf);
     }
-    assert_eq!(cellmap.get_width() % 2, 1);
-    assert_eq!(cellmap.get_height(), cellmap.get_width());
     let elf_point = elf_point.unwrap();
-    assert_eq!(elf_point.x, cellmap.get_width() / 2);
-    assert_eq!(elf_point.y, cellmap.get_height() / 2);
-    //println!("Elf point: {elf_point:?}");
-    println!(
-        "x = {}, y = {}, rule = Advent-2023-21",
-        cellmap.get_width(),
-        cellmap.get_height()
-    );
-    for (y, row) in cellmap.rows().enumerate() {
-        for c in row.iter().copied() {
-            print!(
-                "{}",
-                match c {
-                    Cell::Unknown => '.',
-                    Cell::Rock => 'A',
-                }
-            );
-        }
-        if y as i32 == cellmap.get_height() - 1 {
-            print!("!");
-        } else {
-            print!("$");
-        }
+    let tiling = Tiling::new(factor);
+    let mut cells = tiling.tile(&cellmap).map(|x| *x as u8);
+    cells.set_tile(tiling.to_middle(&cellmap, elf_point), 2);
+    if let Some(rule_path) = rule_path {
+        std::fs::write(&rule_path, RULE_TABLE).unwrap();
+        eprintln!("Wrote the rule to {rule_path}");
     }
+    print!(
+        "{}",
+        Pattern {
+            rule: RULE_NAME.to_string(),
+            generation: None,
+            cells,
+        }
+    );
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use advent_2023::Point3;
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
@@ -15,10 +17,13 @@ impl Brick {
             ],
         }
     }
-    fn fall(&self) -> Brick {
+    /// Returns this brick moved straight down (or up) so that its bottom is
+    /// at the given height.
+    fn with_min_z(&self, min_z: i32) -> Brick {
+        let dz = min_z - self.min_z();
         Brick {
             ends: self.ends.map(|end| Point3 {
-                z: end.z - 1,
+                z: end.z + dz,
                 ..end
             }),
         }
@@ -36,12 +41,6 @@ impl Brick {
             ret
         })
     }
-    fn overlaps_with(&self, other_brick: &Brick) -> bool {
-        // TODO: if this isn't fast enough, do a good job :(
-        self.cells().any(|mycellf| {
-            other_brick.cells().any(|othercell| mycellf == othercell)
-        })
-    }
     fn min_z(&self) -> i32 {
         self.ends[0].z.min(self.ends[1].z)
     }
@@ -50,76 +49,277 @@ impl Brick {
     }
 }
 
-#[derive(Clone)]
-struct Brickstore {
+/// Who's holding up whom, once all the bricks have settled.
+struct SupportGraph {
+    /// The settled bricks, lowest first. Every brick comes after all of the
+    /// bricks it rests on.
     bricks: Vec<Brick>,
-    z_levels: Vec<Vec<usize>>,
+    /// Where each brick was in the input.
+    input_indices: Vec<usize>,
+    /// The bricks each brick is resting directly on. Empty means the ground.
+    rests_on: Vec<Vec<usize>>,
+    /// The bricks resting directly on each brick.
+    supports: Vec<Vec<usize>>,
+    /// The brick that every path from a brick down to the ground must pass
+    /// through, or `None` if the brick can reach the ground some other way.
+    /// (In other words, its immediate dominator, with the ground as the
+    /// root.)
+    dominators: Vec<Option<usize>>,
+    /// How many bricks would fall if each brick were taken away.
+    chain_reactions: Vec<usize>,
 }
 
-impl Brickstore {
-    fn with_bricks(bricks: Vec<Brick>) -> Brickstore {
-        let mut z_levels = vec![];
-        for (n, brick) in bricks.iter().enumerate() {
-            for z in brick.min_z()..=brick.max_z() {
-                while z_levels.len() <= z as usize {
-                    z_levels.push(vec![]);
-                }
-                z_levels[z as usize].push(n);
+impl SupportGraph {
+    /// Drops all the bricks and works out how they support each other.
+    fn settle(bricks: Vec<Brick>) -> SupportGraph {
+        let mut input_indices: Vec<usize> = (0..bricks.len()).collect();
+        input_indices.sort_by_key(|index| bricks[*index].min_z());
+        let mut bricks: Vec<Brick> =
+            input_indices.iter().map(|index| bricks[*index]).collect();
+        // For each column, how high it's stacked and which brick is on top.
+        let mut heightmap: HashMap<(i32, i32), (i32, usize)> = HashMap::new();
+        let mut rests_on = Vec::with_capacity(bricks.len());
+        for (index, brick) in bricks.iter_mut().enumerate() {
+            let columns: Vec<(i32, i32)> =
+                brick.cells().map(|cell| (cell.x, cell.y)).collect();
+            let floor = columns
+                .iter()
+                .filter_map(|column| heightmap.get(column))
+                .map(|(top, _)| *top)
+                .max();
+            let mut below: Vec<usize> = columns
+                .iter()
+                .filter_map(|column| heightmap.get(column))
+                .filter(|(top, _)| Some(*top) == floor)
+                .map(|(_, other)| *other)
+                .collect();
+            below.sort();
+            below.dedup();
+            // The ground is at z = 0, same as before.
+            *brick = brick.with_min_z(floor.map(|x| x + 1).unwrap_or(0));
+            for column in columns {
+                heightmap.insert(column, (brick.max_z(), index));
             }
+            rests_on.push(below);
         }
-        Brickstore { bricks, z_levels }
-    }
-    fn brick_can_fall(
-        &self,
-        brick_index: usize,
-        ignored_index: Option<usize>,
-    ) -> bool {
-        let brick = &self.bricks[brick_index];
-        let min_z = brick.ends[0].z.min(brick.ends[1].z);
-        if min_z == 0 {
-            // GROUND!!!
-            return false;
-        }
-        let below_z = (min_z - 1) as usize;
-        let fallen_brick = brick.fall();
-        for other_brick in self.z_levels[below_z].iter().copied() {
-            if Some(other_brick) == ignored_index {
-                continue;
+        let mut supports = vec![vec![]; bricks.len()];
+        for (index, below) in rests_on.iter().enumerate() {
+            for other in below.iter() {
+                supports[*other].push(index);
             }
-            if self.bricks[other_brick].overlaps_with(&fallen_brick) {
-                return false;
+        }
+        // Every brick comes after the ones it rests on, so its supporters'
+        // dominators are already known, and its own dominator is where their
+        // paths down to the ground first meet.
+        let mut dominators: Vec<Option<usize>> =
+            Vec::with_capacity(bricks.len());
+        let mut depths: Vec<usize> = Vec::with_capacity(bricks.len());
+        for below in rests_on.iter() {
+            let mut meet = below.first().copied();
+            for other in below.iter().skip(1).copied() {
+                meet = meet.and_then(|meet| {
+                    common_dominator(&dominators, &depths, meet, other)
+                });
+            }
+            depths.push(meet.map(|x| depths[x] + 1).unwrap_or(0));
+            dominators.push(meet);
+        }
+        // A brick takes down everything it dominates. Going top down, add
+        // each brick's tally (plus itself) to its dominator's.
+        let mut chain_reactions = vec![0; bricks.len()];
+        for index in (0..bricks.len()).rev() {
+            if let Some(dominator) = dominators[index] {
+                chain_reactions[dominator] += chain_reactions[index] + 1;
             }
         }
-        true
-    }
-    /// Returns true if any bricks fell.
-    fn perform_fall(&mut self) -> bool {
-        let mut bricks_that_may_fall = Vec::with_capacity(128);
-        let mut any_fell = false;
-        for z in 0..self.z_levels.len() {
-            bricks_that_may_fall.clear();
-            for brick_index in self.z_levels[z].iter().copied() {
-                if self.brick_can_fall(brick_index, None) {
-                    bricks_that_may_fall.push(brick_index);
-                }
+        SupportGraph {
+            bricks,
+            input_indices,
+            rests_on,
+            supports,
+            dominators,
+            chain_reactions,
+        }
+    }
+    /// True if taking this brick away wouldn't make anything else fall.
+    fn can_disintegrate(&self, index: usize) -> bool {
+        self.supports[index]
+            .iter()
+            .all(|above| self.rests_on[*above].len() > 1)
+    }
+    /// Prints the graph in Graphviz format, with arrows pointing from each
+    /// brick to the bricks it rests on.
+    fn write_dot(&self) {
+        println!("digraph bricks {{");
+        println!("  ground [shape=box];");
+        for (index, brick) in self.bricks.iter().enumerate() {
+            let [a, b] = brick.ends;
+            println!(
+                "  b{index} [label=\"{index}: {},{},{}~{},{},{}\\n{} fall\"];",
+                a.x, a.y, a.z, b.x, b.y, b.z, self.chain_reactions[index]
+            );
+            if self.rests_on[index].is_empty() {
+                println!("  b{index} -> ground;");
             }
-            for brick_index in bricks_that_may_fall.drain(..) {
-                let brick = &mut self.bricks[brick_index];
-                let gonez = brick.max_z();
-                *brick = brick.fall();
-                let comez = brick.min_z();
-                self.z_levels[gonez as usize].retain(|x| *x != brick_index);
-                self.z_levels[comez as usize].push(brick_index);
-                any_fell = true;
+            for other in self.rests_on[index].iter() {
+                let style = if self.dominators[index] == Some(*other) {
+                    " [style=bold]"
+                } else {
+                    ""
+                };
+                println!("  b{index} -> b{other}{style};");
             }
         }
-        while self.z_levels.last().map(|x| x.is_empty()).unwrap_or(false) {
-            self.z_levels.pop();
+        println!("}}");
+    }
+}
+
+/// A brick to draw, along with the letter it gets and whether to highlight
+/// it.
+#[derive(Debug, Copy, Clone)]
+struct Shown {
+    brick: Brick,
+    label: usize,
+    highlight: bool,
+}
+
+impl Shown {
+    fn letter(&self) -> char {
+        let letter = (b'A' + (self.label % 26) as u8) as char;
+        if self.highlight {
+            letter.to_ascii_lowercase()
+        } else {
+            letter
         }
-        any_fell
+    }
+    /// A color of its own, spread around the color wheel by the golden
+    /// ratio so that neighbors don't look alike. Unhighlighted bricks are
+    /// grey, if anything is highlighted.
+    fn color(&self, highlighting: bool) -> [f64; 3] {
+        if highlighting && !self.highlight {
+            return [0.3, 0.3, 0.3];
+        }
+        let hue = (self.label as f64 * 0.618_033_988_75).fract() * 6.0;
+        let x = 1.0 - (hue % 2.0 - 1.0).abs();
+        let [r, g, b] = match hue as u32 {
+            0 => [1.0, x, 0.0],
+            1 => [x, 1.0, 0.0],
+            2 => [0.0, 1.0, x],
+            3 => [0.0, x, 1.0],
+            4 => [x, 0.0, 1.0],
+            _ => [1.0, 0.0, x],
+        };
+        [r * 0.8 + 0.2, g * 0.8 + 0.2, b * 0.8 + 0.2]
     }
 }
 
+/// Draws the stack from the front (x across, z up) and from the side (y
+/// across, z up), like the puzzle does. Where more than one brick is in line
+/// with a spot, it gets a `?`. Highlighted bricks are in lowercase. Rows are
+/// labelled with `z + z_offset`, so that the ground is always 0 no matter
+/// where the bricks think it is.
+fn render_views(shown: &[Shown], z_offset: i32) -> String {
+    use std::fmt::Write;
+    let mut out = String::new();
+    let max_z = shown.iter().map(|x| x.brick.max_z()).max().unwrap_or(0);
+    for (axis, coord) in [
+        ('x', (|p: Point3| p.x) as fn(Point3) -> i32),
+        ('y', |p: Point3| p.y),
+    ] {
+        let width = shown
+            .iter()
+            .flat_map(|x| x.brick.cells())
+            .map(coord)
+            .max()
+            .unwrap_or(0)
+            + 1;
+        // Which brick is in each spot, by label, since two bricks can share
+        // a letter.
+        let mut view: Vec<Vec<Option<(usize, char)>>> =
+            vec![vec![None; width as usize]; max_z as usize + 1];
+        for brick in shown.iter() {
+            for cell in brick.brick.cells() {
+                let spot = &mut view[cell.z as usize][coord(cell) as usize];
+                *spot = match *spot {
+                    Some((other, _)) if other != brick.label => {
+                        Some((other, '?'))
+                    }
+                    _ => Some((brick.label, brick.letter())),
+                };
+            }
+        }
+        writeln!(out, "{:^width$}", axis, width = width as usize).unwrap();
+        for (z, row) in view.iter().enumerate().rev() {
+            let label = z as i32 + z_offset;
+            if label < 1 {
+                // That's the ground, or below it.
+                continue;
+            }
+            let row: String =
+                row.iter().map(|x| x.map_or('.', |x| x.1)).collect();
+            writeln!(out, "{row} {label}").unwrap();
+        }
+        writeln!(out, "{} 0", "-".repeat(width as usize)).unwrap();
+        writeln!(out).unwrap();
+    }
+    out
+}
+
+/// Writes every cube of every brick out as an OBJ, with vertex colors.
+fn write_obj(path: &str, shown: &[Shown]) -> std::io::Result<()> {
+    use std::fmt::Write;
+    let highlighting = shown.iter().any(|x| x.highlight);
+    let mut out = String::new();
+    let mut vertex_count = 0;
+    for brick in shown.iter() {
+        writeln!(out, "o brick_{}", brick.label).unwrap();
+        let [r, g, b] = brick.color(highlighting);
+        for cell in brick.brick.cells() {
+            for corner in 0..8 {
+                // OBJ has y up, so our z becomes its y.
+                let x = cell.x + (corner & 1);
+                let y = cell.z + ((corner >> 1) & 1);
+                let z = cell.y + ((corner >> 2) & 1);
+                writeln!(out, "v {x} {y} {z} {r:.3} {g:.3} {b:.3}").unwrap();
+            }
+            for face in [
+                [0, 2, 3, 1],
+                [4, 5, 7, 6],
+                [0, 1, 5, 4],
+                [2, 6, 7, 3],
+                [0, 4, 6, 2],
+                [1, 3, 7, 5],
+            ] {
+                let [a, b, c, d] = face.map(|x| x + vertex_count + 1);
+                writeln!(out, "f {a} {b} {c} {d}").unwrap();
+            }
+            vertex_count += 8;
+        }
+    }
+    std::fs::write(path, out)
+}
+
+/// Finds the nearest brick that both `a` and `b` have to go through to reach
+/// the ground, if there is one.
+fn common_dominator(
+    dominators: &[Option<usize>],
+    depths: &[usize],
+    mut a: usize,
+    mut b: usize,
+) -> Option<usize> {
+    while a != b {
+        let deeper = if depths[a] >= depths[b] {
+            &mut a
+        } else {
+            &mut b
+        };
+        // If the deeper one goes straight to the ground, so does the other,
+        // and they never met on the way.
+        *deeper = dominators[*deeper]?;
+    }
+    Some(a)
+}
+
 fn main() {
     let lines = std::io::stdin().lines().map(|x| x.unwrap());
     let all_bricks: Vec<Brick> =
@@ -130,61 +330,117 @@ fn main() {
             + (brick.ends[0].z == brick.ends[1].z) as u8;
         eq >= 2 && brick.ends[0].z.min(brick.ends[1].z) >= 0
     }));
-    let mut brickstore = Brickstore::with_bricks(all_bricks.clone());
-    while brickstore.perform_fall() {}
+    let mut dot = false;
+    let mut view = false;
+    let mut obj_path = None;
+    let mut unsettled = false;
+    let mut highlight_safe = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dot" => dot = true,
+            "--view" => view = true,
+            "--obj" => obj_path = args.next(),
+            "--unsettled" => unsettled = true,
+            "--highlight-safe" => highlight_safe = true,
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
+    let graph = SupportGraph::settle(all_bricks.clone());
+    if dot {
+        graph.write_dot();
+        return;
+    }
+    if view || obj_path.is_some() {
+        let mut shown: Vec<Shown> = all_bricks
+            .iter()
+            .enumerate()
+            .map(|(label, brick)| Shown {
+                brick: *brick,
+                label,
+                highlight: false,
+            })
+            .collect();
+        for (index, input_index) in graph.input_indices.iter().enumerate() {
+            if !unsettled {
+                shown[*input_index].brick = graph.bricks[index];
+            }
+            shown[*input_index].highlight =
+                highlight_safe && graph.can_disintegrate(index);
+        }
+        if view {
+            // The puzzle's ground is at z = 0, and settled bricks have
+            // theirs one lower.
+            let z_offset = if unsettled { 0 } else { 1 };
+            print!("{}", render_views(&shown, z_offset));
+        }
+        if let Some(path) = obj_path {
+            write_obj(&path, &shown).unwrap();
+        }
+    }
     println!(
         "Part 1 answer: {}",
-        (0..brickstore.bricks.len())
-            .filter(|candidate_index| {
-                let candidate_index = *candidate_index;
-                let candidate = &brickstore.bricks[candidate_index];
-                let abovez = (candidate.max_z() + 1) as usize;
-                if abovez < brickstore.z_levels.len() {
-                    // There can be no bricks above us.
-                    for other_brick in
-                        brickstore.z_levels[abovez].iter().copied()
-                    {
-                        if brickstore
-                            .brick_can_fall(other_brick, Some(candidate_index))
-                        {
-                            return false;
-                        }
-                    }
-                }
-                // If we get here, we don't allow any other brick to fall.
-                true
-            })
+        (0..graph.bricks.len())
+            .filter(|index| graph.can_disintegrate(*index))
             .count()
     );
     println!(
         "Part 2 answer: {}",
-        (0..brickstore.bricks.len())
-            .map(|candidate_index| {
-                let mut teststore = brickstore.clone();
-                // Zap the test brick!
-                teststore.bricks[candidate_index] = Brick {
-                    ends: [
-                        Point3 {
-                            x: -999,
-                            y: -999,
-                            z: 0,
-                        },
-                        Point3 {
-                            x: -999,
-                            y: -999,
-                            z: 0,
-                        },
-                    ],
-                };
-                while teststore.perform_fall() {}
-                teststore
-                    .bricks
-                    .iter()
-                    .zip(brickstore.bricks.iter())
-                    .filter(|(a, b)| *a != *b)
-                    .count()
-                    - 1
-            })
-            .sum::<usize>()
+        graph.chain_reactions.iter().sum::<usize>()
+    );
+}
+
+#[test]
+fn test_sample() {
+    let graph = SupportGraph::settle(
+        include_str!("../../sample/22.txt")
+            .lines()
+            .map(Brick::from_str_or_panic)
+            .collect(),
+    );
+    assert_eq!(
+        (0..graph.bricks.len())
+            .filter(|index| graph.can_disintegrate(*index))
+            .count(),
+        5
+    );
+    // Brick A takes down the other six, brick F takes down G.
+    assert_eq!(graph.chain_reactions, vec![6, 0, 0, 0, 0, 1, 0]);
+}
+
+#[test]
+fn test_shared_dominator() {
+    // A diamond on top of one brick: knocking out the bottom takes the whole
+    // diamond with it, even though the top of the diamond has two supports.
+    let graph = SupportGraph::settle(
+        ["0,0,1~2,0,1", "0,0,2~0,0,2", "2,0,2~2,0,2", "0,0,3~2,0,3"]
+            .into_iter()
+            .map(Brick::from_str_or_panic)
+            .collect(),
+    );
+    assert_eq!(graph.dominators, vec![None, Some(0), Some(0), Some(0)]);
+    assert_eq!(graph.chain_reactions, vec![3, 0, 0, 0]);
+}
+
+#[test]
+fn test_views() {
+    let shown = |label, input| Shown {
+        brick: Brick::from_str_or_panic(input),
+        label,
+        highlight: false,
+    };
+    // Both of these are A, but they're still two different bricks, so where
+    // they line up is a `?`. They're straight from the input, so the bottom
+    // row is z = 1 already.
+    let bricks = [shown(0, "0,0,1~1,0,1"), shown(26, "1,0,1~1,1,1")];
+    assert_eq!(
+        render_views(&bricks, 0),
+        "x \nA? 1\n-- 0\n\ny \n?A 1\n-- 0\n\n"
+    );
+    // Settled, the bottom row is z = 0, which the puzzle calls 1.
+    let settled = [shown(0, "0,0,0~0,0,0"), shown(1, "0,0,1~0,0,1")];
+    assert_eq!(
+        render_views(&settled, 1),
+        "x\nB 2\nA 1\n- 0\n\ny\nB 2\nA 1\n- 0\n\n"
     );
 }
@@ -1,6 +1,6 @@
 use std::sync::atomic::{AtomicU32, Ordering};
 
-use advent_2023::{Direction, Point, Tilemap};
+use advent_2023::{bitgrid::BitGrid, Direction, Point, Tilemap};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 enum Tile {
@@ -35,7 +35,7 @@ static LONGEST: AtomicU32 = AtomicU32::new(0);
 
 fn find_path_from(
     tile_map: &Tilemap<Tile>,
-    mut been_map: Tilemap<bool>,
+    mut been_map: BitGrid,
     mut position: Point,
     mut current_length: u32,
 ) -> Option<u32> {
@@ -102,7 +102,7 @@ fn main() {
     assert!(tile_map.get_tile(start_point) == Some(&Tile::Floor));
     assert!(tile_map.get_tile(end_point) == Some(&Tile::Floor));
     let mut been_map =
-        Tilemap::new(tile_map.get_width(), tile_map.get_height());
+        BitGrid::new(tile_map.get_width(), tile_map.get_height());
     been_map.set_tile(start_point, true);
     let longest_path =
         find_path_from(&tile_map, been_map.clone(), start_point, 0).unwrap();
@@ -1,5 +1,4 @@
-use advent_2023::Point3f;
-use rand::prelude::*;
+use advent_2023::{Point3, Point3f};
 
 #[derive(Debug, Copy, Clone, PartialEq)]
 struct Trajectory {
@@ -78,11 +77,7 @@ fn perturbations() -> impl Iterator<Item = Point3f> {
             (-1..=1)
                 .map(move |z| (x, y, z))
                 .filter(|(x, y, z)| *x != 0 || *y != 0 || *z != 0)
-                .map(|(x, y, z)| Point3f {
-                    x: x as f64,
-                    y: y as f64,
-                    z: z as f64,
-                })
+                .map(|(x, y, z)| Point3 { x, y, z }.into())
         })
     })
 }
@@ -111,10 +106,8 @@ fn main() {
         200000000000000.0..=400000000000000.0
     };
     let mut total = 0;
-    for a_index in 0..trajectories.len() {
-        let a = &trajectories[a_index];
-        for b_index in a_index + 1..trajectories.len() {
-            let b = &trajectories[b_index];
+    for (a_index, a) in trajectories.iter().enumerate() {
+        for (b_index, b) in trajectories.iter().enumerate().skip(a_index + 1) {
             let a_slope = a.velocity.y / a.velocity.x;
             let b_slope = b.velocity.y / b.velocity.x;
             let a_intercept = a.position.y - a.position.x * a_slope;
@@ -155,8 +148,7 @@ fn main() {
         }
     }
     println!("Part 1 answer: {total}");
-    let mut rng = thread_rng();
-    let mut rock_pos = Point3f {
+    let rock_pos = Point3f {
         x: 19.0,
         y: 13.0,
         z: 30.0,
@@ -204,70 +196,4 @@ fn main() {
         prev_error = best_error;
         rock_vel = best_vel;
     }
-    println!("Velocity: {rock_vel:?}");
-    return;
-    let mut prev_error: f64 =
-        rock_errors(rock_pos, rock_vel, &trajectories).sum();
-    loop {
-        /*
-        println!(
-            "Pos: {},{},{} Vel: {} {} {} Err: {}",
-            rock_pos.x as i64,
-            rock_pos.y as i64,
-            rock_pos.z as i64,
-            rock_vel.x as i64,
-            rock_vel.y as i64,
-            rock_vel.z as i64,
-            prev_error as i64,
-        );
-        */
-        eprint!("Err: {}           \r", prev_error);
-        if prev_error == 0.0 {
-            break;
-        }
-        let mut best_error = prev_error;
-        let mut best_pos = rock_pos;
-        let mut best_vel = rock_vel;
-        for perturbation in perturbations() {
-            if best_error == 0.0 {
-                break;
-            }
-            let error =
-                rock_errors(rock_pos + perturbation, rock_vel, &trajectories)
-                    .sum();
-            if error < best_error {
-                best_error = error;
-                best_pos = rock_pos + perturbation;
-                best_vel = rock_vel;
-            }
-            let error =
-                rock_errors(rock_pos, rock_vel + perturbation, &trajectories)
-                    .sum();
-            if error < best_error {
-                best_error = error;
-                best_pos = rock_pos;
-                best_vel = rock_vel + perturbation;
-            }
-        }
-        if best_error < prev_error {
-            prev_error = best_error;
-            rock_pos = best_pos;
-            rock_vel = best_vel;
-        } else if best_error == prev_error {
-            // give up!
-            //println!();
-            rock_pos = Point3f {
-                x: rng.gen_range(test_range.clone()).floor(),
-                y: rng.gen_range(test_range.clone()).floor(),
-                z: rng.gen_range(test_range.clone()).floor(),
-            };
-            rock_vel = Point3f {
-                x: 0.0,
-                y: 0.0,
-                z: 0.0,
-            };
-            prev_error = rock_errors(rock_pos, rock_vel, &trajectories).sum();
-        }
-    }
-    println!("Part 2 answer: {}", rock_pos.x + rock_pos.y + rock_pos.z);
 }
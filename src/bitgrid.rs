@@ -0,0 +1,410 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    ops::{BitAnd, BitOr, BitXor, Not},
+};
+
+use crate::{Direction, Point, Tilemap};
+
+/// A `Tilemap<bool>` squeezed down to one bit per tile. Each row starts on a
+/// fresh `u64`, and bit `x % 64` of word `x / 64` is column `x`. Bits past
+/// the right edge are always zero, so whole-word operations can ignore the
+/// edge.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitGrid {
+    words: Vec<u64>,
+    width: i32,
+    height: i32,
+    words_per_row: usize,
+}
+
+/// Every bit set in `word`, lowest first.
+pub fn set_bits(mut word: u64) -> impl Iterator<Item = u32> {
+    std::iter::from_fn(move || {
+        if word == 0 {
+            return None;
+        }
+        let bit = word.trailing_zeros();
+        word &= word - 1;
+        Some(bit)
+    })
+}
+
+impl BitGrid {
+    pub fn new(width: i32, height: i32) -> BitGrid {
+        let words_per_row = (width as usize).div_ceil(64);
+        BitGrid {
+            words: vec![0; words_per_row * height as usize],
+            width,
+            height,
+            words_per_row,
+        }
+    }
+    pub fn new_empty() -> BitGrid {
+        BitGrid::new(0, 0)
+    }
+    pub fn add_row(&mut self, row: &[bool]) {
+        if self.height == 0 {
+            self.width = row.len() as i32;
+            self.words_per_row = row.len().div_ceil(64);
+        } else {
+            assert_eq!(
+                self.width,
+                row.len() as i32,
+                "Tried to add a row that was the wrong length!"
+            );
+        }
+        let start = self.words.len();
+        self.words.resize(start + self.words_per_row, 0);
+        for (x, bit) in row.iter().enumerate() {
+            self.words[start + x / 64] |= (*bit as u64) << (x % 64);
+        }
+        self.height += 1;
+    }
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
+    fn index_of(&self, point: Point) -> Option<(usize, u32)> {
+        if point.x < 0
+            || point.x >= self.width
+            || point.y < 0
+            || point.y >= self.height
+        {
+            None
+        } else {
+            Some((
+                point.y as usize * self.words_per_row + point.x as usize / 64,
+                point.x as u32 % 64,
+            ))
+        }
+    }
+    pub fn get_tile(&self, point: Point) -> Option<bool> {
+        self.index_of(point)
+            .map(|(word, bit)| (self.words[word] >> bit) & 1 != 0)
+    }
+    pub fn set_tile(&mut self, point: Point, value: bool) {
+        let (word, bit) = self
+            .index_of(point)
+            .expect("Can't set_tile outside the bounds of the map!");
+        self.words[word] =
+            (self.words[word] & !(1 << bit)) | ((value as u64) << bit);
+    }
+    /// Flips one tile, returning what it is now.
+    pub fn toggle(&mut self, point: Point) -> bool {
+        let (word, bit) = self
+            .index_of(point)
+            .expect("Can't toggle outside the bounds of the map!");
+        self.words[word] ^= 1 << bit;
+        (self.words[word] >> bit) & 1 != 0
+    }
+    /// Every tile, top to bottom, left to right.
+    pub fn iter(&self) -> impl '_ + Iterator<Item = bool> {
+        (0..self.height).flat_map(move |y| {
+            (0..self.width)
+                .map(move |x| self.get_tile(Point { x, y }).unwrap())
+        })
+    }
+    /// Where all the set tiles are, top to bottom, left to right.
+    pub fn find_tiles(&self) -> impl '_ + Iterator<Item = Point> {
+        self.words
+            .iter()
+            .enumerate()
+            .flat_map(move |(index, word)| {
+                let y = (index / self.words_per_row) as i32;
+                let base = (index % self.words_per_row) as i32 * 64;
+                set_bits(*word).map(move |bit| Point {
+                    x: base + bit as i32,
+                    y,
+                })
+            })
+    }
+    /// How many tiles are set.
+    pub fn count_ones(&self) -> u32 {
+        self.words.iter().map(|x| x.count_ones()).sum()
+    }
+    /// Row `y` as an integer, with column 0 in the lowest bit. Only works on
+    /// grids up to 64 wide.
+    pub fn row_bits(&self, y: i32) -> Option<u64> {
+        assert!(self.width <= 64, "rows this wide don't fit in a u64");
+        if y < 0 || y >= self.height {
+            return None;
+        }
+        Some(
+            self.words
+                .get(y as usize * self.words_per_row)
+                .map_or(0, |x| *x),
+        )
+    }
+    /// Column `x` as an integer, with row 0 in the lowest bit. Only works on
+    /// grids up to 64 tall.
+    pub fn column_bits(&self, x: i32) -> Option<u64> {
+        assert!(self.height <= 64, "columns this tall don't fit in a u64");
+        if x < 0 || x >= self.width {
+            return None;
+        }
+        let (word, bit) = (x as usize / 64, x as u32 % 64);
+        Some((0..self.height as usize).fold(0, |acc, y| {
+            let cell = (self.words[y * self.words_per_row + word] >> bit) & 1;
+            acc | (cell << y)
+        }))
+    }
+    pub fn transpose(&self) -> BitGrid {
+        let mut ret = BitGrid::new(self.height, self.width);
+        for point in self.find_tiles() {
+            ret.set_tile(
+                Point {
+                    x: point.y,
+                    y: point.x,
+                },
+                true,
+            );
+        }
+        ret
+    }
+    /// Everything moved one step towards `dir`. Whatever goes off the edge is
+    /// lost, and the opposite edge comes in empty.
+    pub fn shifted(&self, dir: Direction) -> BitGrid {
+        let mut ret = BitGrid::new(self.width, self.height);
+        let stride = self.words_per_row;
+        if stride == 0 {
+            return ret;
+        }
+        match dir {
+            Direction::North => {
+                ret.words[..self.words.len().saturating_sub(stride)]
+                    .copy_from_slice(
+                        &self.words[stride.min(self.words.len())..],
+                    );
+            }
+            Direction::South => {
+                let len = self.words.len().saturating_sub(stride);
+                ret.words[stride.min(self.words.len())..]
+                    .copy_from_slice(&self.words[..len]);
+            }
+            Direction::East => {
+                for (dest, src) in ret
+                    .words
+                    .chunks_exact_mut(stride)
+                    .zip(self.words.chunks_exact(stride))
+                {
+                    let mut carry = 0;
+                    for (dest, src) in dest.iter_mut().zip(src.iter()) {
+                        *dest = (src << 1) | carry;
+                        carry = src >> 63;
+                    }
+                }
+                ret.clear_past_edge();
+            }
+            Direction::West => {
+                for (dest, src) in ret
+                    .words
+                    .chunks_exact_mut(stride)
+                    .zip(self.words.chunks_exact(stride))
+                {
+                    let mut carry = 0;
+                    for (dest, src) in dest.iter_mut().zip(src.iter()).rev() {
+                        *dest = (src >> 1) | carry;
+                        carry = src << 63;
+                    }
+                }
+            }
+        }
+        ret
+    }
+    /// Every tile next to one that's set, whether or not it's set itself:
+    /// where an elf wandering around a garden could be one step later.
+    pub fn neighbors(&self) -> BitGrid {
+        Direction::ALL
+            .iter()
+            .fold(BitGrid::new(self.width, self.height), |acc, dir| {
+                acc | self.shifted(*dir)
+            })
+    }
+    /// Every tile that's set or next to one that's set: one step of a flood
+    /// fill.
+    pub fn spread(&self) -> BitGrid {
+        self.neighbors() | self.clone()
+    }
+    /// Zeroes the bits past the right edge of each row, which shifting east
+    /// or inverting can set.
+    fn clear_past_edge(&mut self) {
+        let used = self.width as u32 % 64;
+        if used == 0 || self.words_per_row == 0 {
+            return;
+        }
+        let mask = (1u64 << used) - 1;
+        for row in self.words.chunks_exact_mut(self.words_per_row) {
+            *row.last_mut().unwrap() &= mask;
+        }
+    }
+    fn zip_words(&self, other: &BitGrid, f: impl Fn(u64, u64) -> u64) -> Self {
+        assert_eq!(
+            (self.width, self.height),
+            (other.width, other.height),
+            "grids have to be the same size"
+        );
+        BitGrid {
+            words: self
+                .words
+                .iter()
+                .zip(other.words.iter())
+                .map(|(a, b)| f(*a, *b))
+                .collect(),
+            ..*self
+        }
+    }
+    pub fn to_tilemap(&self) -> Tilemap<bool> {
+        let mut ret = Tilemap::new(self.width, self.height);
+        for point in self.find_tiles() {
+            ret.set_tile(point, true);
+        }
+        ret
+    }
+}
+
+impl From<&Tilemap<bool>> for BitGrid {
+    fn from(value: &Tilemap<bool>) -> Self {
+        let mut ret = BitGrid::new(value.get_width(), value.get_height());
+        for point in value.find_tiles(|x| *x) {
+            ret.set_tile(point, true);
+        }
+        ret
+    }
+}
+
+impl BitAnd for &BitGrid {
+    type Output = BitGrid;
+    fn bitand(self, rhs: Self) -> BitGrid {
+        self.zip_words(rhs, |a, b| a & b)
+    }
+}
+
+impl BitOr for &BitGrid {
+    type Output = BitGrid;
+    fn bitor(self, rhs: Self) -> BitGrid {
+        self.zip_words(rhs, |a, b| a | b)
+    }
+}
+
+impl BitXor for &BitGrid {
+    type Output = BitGrid;
+    fn bitxor(self, rhs: Self) -> BitGrid {
+        self.zip_words(rhs, |a, b| a ^ b)
+    }
+}
+
+impl BitAnd for BitGrid {
+    type Output = BitGrid;
+    fn bitand(self, rhs: Self) -> BitGrid {
+        &self & &rhs
+    }
+}
+
+impl BitOr for BitGrid {
+    type Output = BitGrid;
+    fn bitor(self, rhs: Self) -> BitGrid {
+        &self | &rhs
+    }
+}
+
+impl BitXor for BitGrid {
+    type Output = BitGrid;
+    fn bitxor(self, rhs: Self) -> BitGrid {
+        &self ^ &rhs
+    }
+}
+
+impl Not for &BitGrid {
+    type Output = BitGrid;
+    fn not(self) -> BitGrid {
+        let mut ret = BitGrid {
+            words: self.words.iter().map(|x| !x).collect(),
+            ..*self
+        };
+        ret.clear_past_edge();
+        ret
+    }
+}
+
+impl Not for BitGrid {
+    type Output = BitGrid;
+    fn not(self) -> BitGrid {
+        !&self
+    }
+}
+
+impl Display for BitGrid {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let set = self.get_tile(Point { x, y }).unwrap();
+                write!(f, "{}", if set { '#' } else { '.' })?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> BitGrid {
+        let mut ret = BitGrid::new_empty();
+        for line in text.lines() {
+            ret.add_row(&line.chars().map(|x| x == '#').collect::<Vec<_>>());
+        }
+        ret
+    }
+
+    #[test]
+    fn rows_and_columns() {
+        let grid = parse("#..#\n.#..\n##.#");
+        assert_eq!(grid.row_bits(0), Some(0b1001));
+        assert_eq!(grid.row_bits(2), Some(0b1011));
+        assert_eq!(grid.column_bits(1), Some(0b110));
+        assert_eq!(grid.column_bits(4), None);
+        assert_eq!(grid.count_ones(), 6);
+        assert_eq!(grid.transpose().row_bits(3), grid.column_bits(3));
+        assert_eq!(BitGrid::from(&grid.to_tilemap()), grid);
+    }
+
+    #[test]
+    fn wide_shifts() {
+        // Wide enough to cross a word boundary, and then some.
+        let mut grid = BitGrid::new(130, 3);
+        grid.set_tile(Point { x: 63, y: 1 }, true);
+        grid.set_tile(Point { x: 129, y: 1 }, true);
+        let east = grid.shifted(Direction::East);
+        assert_eq!(
+            east.find_tiles().collect::<Vec<_>>(),
+            [Point { x: 64, y: 1 }]
+        );
+        let west = grid.shifted(Direction::West);
+        assert_eq!(
+            west.find_tiles().collect::<Vec<_>>(),
+            [Point { x: 62, y: 1 }, Point { x: 128, y: 1 }]
+        );
+        assert_eq!(grid.shifted(Direction::North).count_ones(), 2);
+        assert_eq!(
+            grid.shifted(Direction::South).shifted(Direction::South),
+            BitGrid::new(130, 3)
+        );
+        assert_eq!(grid.spread().count_ones(), 9);
+        assert_eq!(grid.neighbors().count_ones(), 7);
+        assert_eq!((!&grid).count_ones(), 130 * 3 - 2);
+    }
+
+    #[test]
+    fn bitwise() {
+        let a = parse("##..\n#.#.");
+        let b = parse("#.#.\n#..#");
+        assert_eq!(&a & &b, parse("#...\n#..."));
+        assert_eq!(&a | &b, parse("###.\n#.##"));
+        assert_eq!((&a ^ &b).count_ones(), 4);
+        assert_eq!(!a, parse("..##\n.#.#"));
+    }
+}
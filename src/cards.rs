@@ -0,0 +1,405 @@
+use std::cmp::Ordering;
+
+use anyhow::{anyhow, bail};
+
+/// Every kind of hand, weakest first. Camel Cards never makes straights or
+/// flushes, and everything else is in the same order as in poker. With
+/// wildcards around, five of a kind beats a straight flush.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone, Hash)]
+pub enum HandKind {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourKind,
+    StraightFlush,
+    FiveKind,
+}
+
+/// How to settle two hands of the same kind.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum TieBreak {
+    /// Look at the cards one at a time, in the order they're in, until one
+    /// is higher. This is what Camel Cards does.
+    CardByCard,
+    /// Look at the biggest group of cards first, then the next biggest, and
+    /// so on, with higher ranks first among groups the same size. This is
+    /// poker's "kickers".
+    Groups,
+}
+
+/// One card. Ranks are where the card sits in `RuleSet::ranks`, so higher
+/// is better.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct Card {
+    pub rank: u8,
+    pub suit: Option<char>,
+}
+
+/// Everything that makes one card game different from another.
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    /// Every rank there is, weakest first.
+    pub ranks: Vec<char>,
+    /// Ranks that pretend to be whatever makes the hand best. When it comes
+    /// to tie breaks, they're just their place in `ranks`.
+    pub wildcards: Vec<char>,
+    /// Whether to sort the hand, best card first, before anything looks at
+    /// it. Camel Cards doesn't, which is the whole trick of part 1.
+    pub canonicalize: bool,
+    pub tie_break: TieBreak,
+    pub hand_size: usize,
+    /// Whether `hand_size` ranks in a row make a straight. The best rank can
+    /// also go below the worst one, like the ace in A-2-3-4-5, and then it
+    /// counts as the lowest card of the hand.
+    pub straights: bool,
+    /// Whether cards have suits (written after the rank, like `Ah`), and all
+    /// of one suit makes a flush.
+    pub flushes: bool,
+}
+
+impl RuleSet {
+    /// Part 1.
+    pub fn camel_cards() -> RuleSet {
+        RuleSet {
+            ranks: "23456789TJQKA".chars().collect(),
+            wildcards: vec![],
+            canonicalize: false,
+            tie_break: TieBreak::CardByCard,
+            hand_size: 5,
+            straights: false,
+            flushes: false,
+        }
+    }
+    /// Part 2: `J` is a joker now, and the weakest card on its own.
+    pub fn camel_cards_with_jokers() -> RuleSet {
+        RuleSet {
+            ranks: "J23456789TQKA".chars().collect(),
+            wildcards: vec!['J'],
+            ..RuleSet::camel_cards()
+        }
+    }
+    /// Good old five card poker, no wildcards, aces high or low.
+    pub fn poker() -> RuleSet {
+        RuleSet {
+            canonicalize: true,
+            tie_break: TieBreak::Groups,
+            straights: true,
+            flushes: true,
+            ..RuleSet::camel_cards()
+        }
+    }
+    /// Looks one of the above up by name, for command lines.
+    pub fn named(name: &str) -> Option<RuleSet> {
+        match name {
+            "camel" => Some(RuleSet::camel_cards()),
+            "jokers" => Some(RuleSet::camel_cards_with_jokers()),
+            "poker" => Some(RuleSet::poker()),
+            _ => None,
+        }
+    }
+    fn rank_of(&self, c: char) -> anyhow::Result<u8> {
+        self.ranks
+            .iter()
+            .position(|x| *x == c)
+            .map(|x| x as u8)
+            .ok_or(anyhow!("{c:?} isn't a rank"))
+    }
+    fn is_wild(&self, card: &Card) -> bool {
+        self.wildcards.contains(&self.ranks[card.rank as usize])
+    }
+    /// Reads something like `KTJJT`, or `AhKhQhJhTh` if there are suits.
+    pub fn parse_cards(&self, s: &str) -> anyhow::Result<Vec<Card>> {
+        let mut chars = s.chars();
+        let mut cards = Vec::with_capacity(self.hand_size);
+        while let Some(c) = chars.next() {
+            let suit = if self.flushes {
+                Some(chars.next().ok_or(anyhow!("{c:?} has no suit"))?)
+            } else {
+                None
+            };
+            cards.push(Card {
+                rank: self.rank_of(c)?,
+                suit,
+            });
+        }
+        if cards.len() != self.hand_size {
+            bail!("{s:?} has {} cards, not {}", cards.len(), self.hand_size);
+        }
+        Ok(cards)
+    }
+    /// Reads a line like `KTJJT 220`.
+    pub fn parse_hand(&self, line: &str) -> anyhow::Result<Hand> {
+        let (cards, bid) =
+            line.split_once(' ').ok_or(anyhow!("no bid in {line:?}"))?;
+        Ok(Hand::new(
+            self,
+            self.parse_cards(cards)?,
+            bid.trim().parse()?,
+        ))
+    }
+    pub fn kind_of(&self, cards: &[Card]) -> HandKind {
+        // A strange, but low effort, method of determining the hand...
+        let mut counts = vec![0; self.ranks.len()];
+        let mut wild = 0;
+        for card in cards.iter() {
+            if self.is_wild(card) {
+                wild += 1;
+            } else {
+                counts[card.rank as usize] += 1;
+            }
+        }
+        counts.sort_by(|a, b| a.cmp(b).reverse());
+        // The wildcards always do best joining the biggest group.
+        let groups = (counts[0] + wild, counts.get(1).copied().unwrap_or(0));
+        let of_a_kind = match groups {
+            (5.., _) => HandKind::FiveKind,
+            (4, _) => HandKind::FourKind,
+            (3, 2..) => HandKind::FullHouse,
+            (3, _) => HandKind::ThreeKind,
+            (2, 2..) => HandKind::TwoPair,
+            (2, _) => HandKind::OnePair,
+            (_, _) => HandKind::HighCard,
+        };
+        let straight = self.straights && self.straight(cards).is_some();
+        let flush = self.flushes && self.is_flush(cards);
+        let special = match (straight, flush) {
+            (true, true) => HandKind::StraightFlush,
+            (false, true) => HandKind::Flush,
+            (true, false) => HandKind::Straight,
+            (false, false) => HandKind::HighCard,
+        };
+        of_a_kind.max(special)
+    }
+    /// Whether the cards make a straight: `Some(false)` if they do, and
+    /// `Some(true)` if they only do with the best rank going low, or `None`
+    /// if they don't at all. The cards that aren't wild need to be all
+    /// different and close enough together that the wildcards can fill in
+    /// the rest of a run.
+    fn straight(&self, cards: &[Card]) -> Option<bool> {
+        // Wildcards don't take up a place in the run.
+        let natural: Vec<u8> = (0..self.ranks.len() as u8)
+            .filter(|x| !self.wildcards.contains(&self.ranks[*x as usize]))
+            .collect();
+        if natural.len() < self.hand_size {
+            return None;
+        }
+        let top = natural.len() as i32 - 1;
+        let mut places: Vec<i32> = cards
+            .iter()
+            .filter(|x| !self.is_wild(x))
+            .map(|x| natural.iter().position(|y| *y == x.rank).unwrap() as i32)
+            .collect();
+        let fits = |places: &mut Vec<i32>| {
+            places.sort_unstable();
+            let distinct = places.windows(2).all(|x| x[0] != x[1]);
+            let span = match (places.first(), places.last()) {
+                (Some(low), Some(high)) => (high - low + 1) as usize,
+                _ => 0,
+            };
+            distinct && span <= self.hand_size
+        };
+        if fits(&mut places) {
+            return Some(false);
+        }
+        for place in places.iter_mut().filter(|x| **x == top) {
+            *place = -1;
+        }
+        fits(&mut places).then_some(true)
+    }
+    fn is_flush(&self, cards: &[Card]) -> bool {
+        let mut suits =
+            cards.iter().filter(|x| !self.is_wild(x)).map(|x| x.suit);
+        match suits.next() {
+            Some(first) => suits.all(|x| x == first),
+            None => true,
+        }
+    }
+    /// The ranks to compare, in order, when two hands are the same kind.
+    fn tie_break_order(&self, cards: &[Card]) -> Vec<u8> {
+        let mut ranks: Vec<u8> = cards.iter().map(|x| x.rank).collect();
+        if self.canonicalize {
+            ranks.sort_by(|a, b| a.cmp(b).reverse());
+        }
+        if self.tie_break == TieBreak::Groups {
+            let mut counts = vec![0; self.ranks.len()];
+            for rank in ranks.iter() {
+                counts[*rank as usize] += 1;
+            }
+            // Stable, so same-size groups keep whatever order they had.
+            ranks.sort_by_key(|x| std::cmp::Reverse(counts[*x as usize]));
+        }
+        if self.canonicalize
+            && self.straights
+            && self.straight(cards) == Some(true)
+        {
+            // The best rank went low to make the straight, so it's the
+            // worst card now.
+            let top = (0..self.ranks.len() as u8)
+                .rev()
+                .find(|x| !self.wildcards.contains(&self.ranks[*x as usize]));
+            ranks.sort_by_key(|x| Some(*x) == top);
+        }
+        ranks
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Hand {
+    pub cards: Vec<Card>,
+    pub bid: u32,
+    pub kind: HandKind,
+    tie_break: Vec<u8>,
+}
+
+impl Hand {
+    pub fn new(rules: &RuleSet, cards: Vec<Card>, bid: u32) -> Hand {
+        Hand {
+            kind: rules.kind_of(&cards),
+            tie_break: rules.tie_break_order(&cards),
+            cards,
+            bid,
+        }
+    }
+}
+
+impl PartialEq for Hand {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Hand {}
+
+impl PartialOrd for Hand {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Hand {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match self.kind.cmp(&other.kind) {
+            // If equal, we need to look at the cards.
+            Ordering::Equal => self.tie_break.cmp(&other.tie_break),
+            // If not equal, the kind was enough to decide.
+            x => x,
+        }
+    }
+}
+
+/// Ranks the hands, weakest first, and adds up each bid times its rank.
+pub fn total_winnings(mut hands: Vec<Hand>) -> u64 {
+    hands.sort();
+    hands
+        .iter()
+        .enumerate()
+        .fold(0u64, |accumulator, (index, hand)| {
+            accumulator + (index + 1) as u64 * hand.bid as u64
+        })
+}
+
+/// Everything both parts of day 7 do: read `--rules` and `--hand-size` off
+/// the command line, starting from `rules`, then score the hands on stdin.
+/// The hand size goes on last, so it sticks whichever order they're in.
+pub fn run(mut rules: RuleSet, part: u32) {
+    let mut hand_size = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--rules" => {
+                let name = args.next().expect("--rules needs a name");
+                rules = RuleSet::named(&name)
+                    .unwrap_or_else(|| panic!("No rules called {name:?}"));
+            }
+            "--hand-size" => {
+                hand_size = Some(
+                    args.next()
+                        .expect("--hand-size needs a number")
+                        .parse()
+                        .expect("--hand-size needs a number"),
+                )
+            }
+            _ => panic!("Unknown argument: {arg:?}"),
+        }
+    }
+    if let Some(hand_size) = hand_size {
+        rules.hand_size = hand_size;
+    }
+    let hands = std::io::stdin()
+        .lines()
+        .map(|line| rules.parse_hand(&line.unwrap()).unwrap())
+        .collect();
+    println!("Puzzle {part} answer: {}", total_winnings(hands));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = include_str!("../sample/07.txt");
+
+    fn winnings(rules: &RuleSet) -> u64 {
+        total_winnings(
+            SAMPLE
+                .lines()
+                .map(|x| rules.parse_hand(x).unwrap())
+                .collect(),
+        )
+    }
+
+    fn kind(rules: &RuleSet, cards: &str) -> HandKind {
+        rules.kind_of(&rules.parse_cards(cards).unwrap())
+    }
+
+    #[test]
+    fn camel_cards() {
+        assert_eq!(winnings(&RuleSet::camel_cards()), 6440);
+        assert_eq!(winnings(&RuleSet::camel_cards_with_jokers()), 5905);
+        let jokers = RuleSet::camel_cards_with_jokers();
+        assert_eq!(kind(&jokers, "JJJJJ"), HandKind::FiveKind);
+        assert_eq!(kind(&jokers, "QJJQ2"), HandKind::FourKind);
+        assert_eq!(kind(&jokers, "2345J"), HandKind::OnePair);
+    }
+
+    #[test]
+    fn poker() {
+        let rules = RuleSet::poker();
+        assert_eq!(kind(&rules, "9cTdJhQsKc"), HandKind::Straight);
+        assert_eq!(kind(&rules, "2h7h9hJhAh"), HandKind::Flush);
+        assert_eq!(kind(&rules, "9hThJhQhKh"), HandKind::StraightFlush);
+        assert_eq!(kind(&rules, "AcAdAh2c2d"), HandKind::FullHouse);
+        // Aces can be low, but they can't wrap around.
+        assert_eq!(kind(&rules, "Ac2d3h4s5c"), HandKind::Straight);
+        assert_eq!(kind(&rules, "Kc2d3h4sAc"), HandKind::HighCard);
+        assert_eq!(kind(&rules, "Ah2h3h4h5h"), HandKind::StraightFlush);
+        // And then it's the lowest straight there is.
+        let wheel = rules.parse_hand("5c4d3h2sAc 1").unwrap();
+        let six_high = rules.parse_hand("6c5d4h3s2c 2").unwrap();
+        let broadway = rules.parse_hand("AcKdQhJsTc 3").unwrap();
+        assert!(wheel < six_high && six_high < broadway);
+        // Kickers: the pair matters more than the cards next to it.
+        let a = rules.parse_hand("AcKd3h3s2c 1").unwrap();
+        let b = rules.parse_hand("4c4dAhKs2c 2").unwrap();
+        assert!(a < b);
+        let wild = RuleSet {
+            wildcards: vec!['2'],
+            ..RuleSet::poker()
+        };
+        assert_eq!(kind(&wild, "9cTd2hQsKc"), HandKind::Straight);
+    }
+
+    #[test]
+    fn other_sizes() {
+        let rules = RuleSet {
+            hand_size: 3,
+            ..RuleSet::camel_cards_with_jokers()
+        };
+        assert_eq!(kind(&rules, "KKJ"), HandKind::ThreeKind);
+        assert_eq!(kind(&rules, "K2J"), HandKind::OnePair);
+        assert!(rules.parse_cards("KKKK").is_err());
+    }
+}
@@ -0,0 +1,189 @@
+use std::{
+    fmt::{Display, Formatter, Result as FmtResult},
+    str::FromStr,
+};
+
+use anyhow::{anyhow, bail};
+
+use crate::{Point, Tilemap};
+
+/// A pattern in Golly's extended RLE format. Cells are state numbers: 0 is
+/// empty, and the rest are whatever the rule says they are.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pattern {
+    pub rule: String,
+    /// The generation count Golly saved along with the pattern, if any.
+    pub generation: Option<u64>,
+    pub cells: Tilemap<u8>,
+}
+
+/// Golly only goes up to 24 states with single letters, which is plenty for
+/// anything we're doing.
+const MAX_STATE: u8 = 24;
+
+fn state_char(state: u8) -> char {
+    match state {
+        0 => '.',
+        1..=MAX_STATE => (b'A' + state - 1) as char,
+        _ => panic!("state {state} is too big for this RLE writer"),
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        if let Some(generation) = self.generation {
+            writeln!(f, "#CXRLE Pos=0,0 Gen={generation}")?;
+        }
+        writeln!(
+            f,
+            "x = {}, y = {}, rule = {}",
+            self.cells.get_width(),
+            self.cells.get_height(),
+            self.rule
+        )?;
+        // Golly wants lines no longer than 70 characters.
+        let mut line = String::with_capacity(80);
+        let mut push = |f: &mut Formatter<'_>, count: usize, ch: char| {
+            let token = if count == 1 {
+                ch.to_string()
+            } else {
+                format!("{count}{ch}")
+            };
+            if line.len() + token.len() > 70 {
+                writeln!(f, "{line}")?;
+                line.clear();
+            }
+            line.push_str(&token);
+            Ok(())
+        };
+        let mut blank_rows = 0;
+        for (y, row) in self.cells.rows().enumerate() {
+            // Empty cells at the end of a row go without saying.
+            let used = row.iter().rposition(|x| *x != 0).map_or(0, |x| x + 1);
+            if used == 0 && y != 0 {
+                blank_rows += 1;
+                continue;
+            }
+            if y != 0 {
+                push(f, blank_rows + 1, '$')?;
+            }
+            blank_rows = 0;
+            let mut cells = row[..used].iter().copied().peekable();
+            while let Some(state) = cells.next() {
+                let mut count = 1;
+                while cells.next_if_eq(&state).is_some() {
+                    count += 1;
+                }
+                push(f, count, state_char(state))?;
+            }
+        }
+        push(f, 1, '!')?;
+        writeln!(f, "{line}")
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Pattern> {
+        let mut lines = s.lines();
+        let mut generation = None;
+        let header = loop {
+            let line = lines.next().ok_or(anyhow!("no RLE header"))?;
+            if let Some(comment) = line.strip_prefix('#') {
+                if let Some((_, gen)) = comment.split_once("Gen=") {
+                    let gen = gen.split_whitespace().next().unwrap_or("");
+                    generation = Some(gen.parse()?);
+                }
+            } else if !line.trim().is_empty() {
+                break line;
+            }
+        };
+        let mut width = None;
+        let mut height = None;
+        let mut rule = "B3/S23".to_string();
+        for field in header.split(',') {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or(anyhow!("bad RLE header field {field:?}"))?;
+            match key.trim() {
+                "x" => width = Some(value.trim().parse()?),
+                "y" => height = Some(value.trim().parse()?),
+                "rule" => rule = value.trim().to_string(),
+                key => bail!("unknown RLE header field {key:?}"),
+            }
+        }
+        let (Some(width), Some(height)) = (width, height) else {
+            bail!("RLE header is missing the size");
+        };
+        let mut cells = Tilemap::new(width, height);
+        let mut pos = Point { x: 0, y: 0 };
+        let mut count: Option<i32> = None;
+        'outer: for line in lines {
+            for ch in line.chars() {
+                if let Some(digit) = ch.to_digit(10) {
+                    count = Some(count.unwrap_or(0) * 10 + digit as i32);
+                    continue;
+                }
+                let run = count.take().unwrap_or(1);
+                let state = match ch {
+                    '!' => break 'outer,
+                    '$' => {
+                        pos = Point {
+                            x: 0,
+                            y: pos.y + run,
+                        };
+                        continue;
+                    }
+                    '.' | 'b' => 0,
+                    'o' => 1,
+                    'A'..='X' => ch as u8 - b'A' + 1,
+                    _ if ch.is_whitespace() => continue,
+                    _ => bail!("unsupported RLE cell {ch:?}"),
+                };
+                for _ in 0..run {
+                    if state != 0 {
+                        *cells.get_tile_mut(pos).ok_or(anyhow!(
+                            "RLE pattern is bigger than its header says"
+                        ))? = state;
+                    }
+                    pos.x += 1;
+                }
+            }
+        }
+        Ok(Pattern {
+            rule,
+            generation,
+            cells,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut cells = Tilemap::new_empty();
+        cells.add_row(&[1, 1, 1, 0, 2, 0, 0]);
+        cells.add_row(&[0, 0, 0, 0, 0, 0, 0]);
+        cells.add_row(&[0, 0, 0, 0, 0, 0, 0]);
+        cells.add_row(&[3, 0, 0, 0, 0, 0, 24]);
+        let pattern = Pattern {
+            rule: "Test".to_string(),
+            generation: Some(42),
+            cells,
+        };
+        let text = pattern.to_string();
+        assert!(text.ends_with("x = 7, y = 4, rule = Test\n3A.B3$C5.X!\n"));
+        assert_eq!(text.parse::<Pattern>().unwrap(), pattern);
+    }
+
+    #[test]
+    fn two_state() {
+        let glider: Pattern = "x = 3, y = 3\nbo$2bo$3o!".parse().unwrap();
+        assert_eq!(glider.rule, "B3/S23");
+        assert_eq!(glider.cells.iter().filter(|x| **x == 1).count(), 5);
+        assert_eq!(glider.cells.get_tile(Point { x: 2, y: 1 }), Some(&1));
+    }
+}
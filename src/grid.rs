@@ -0,0 +1,348 @@
+use std::collections::HashMap;
+
+use crate::{Point, Tilemap};
+
+/// A `Tilemap` that can have tiles at any coordinates, including negative
+/// ones. It starts out empty, and `set_tile` makes it bigger whenever you
+/// write outside of it, so you don't have to know how big the map is going
+/// to be before you start drawing on it. Every tile you haven't set is
+/// `blank`, and like a `Tilemap`, anywhere outside `bounds` is `None`.
+#[derive(Clone, Debug)]
+pub struct GrowingTilemap<T: Clone> {
+    tiles: Tilemap<T>,
+    /// The coordinates of the top left corner of `tiles`.
+    origin: Point,
+    blank: T,
+    /// The smallest and largest corners of everything that's been set.
+    used: Option<(Point, Point)>,
+}
+
+impl<T: Clone> GrowingTilemap<T> {
+    pub fn new(blank: T) -> GrowingTilemap<T> {
+        GrowingTilemap {
+            tiles: Tilemap::new_empty(),
+            origin: Point { x: 0, y: 0 },
+            blank,
+            used: None,
+        }
+    }
+    /// Whether `point` is inside `bounds`. There's usually room for more
+    /// than that, but nobody outside needs to know.
+    fn in_bounds(&self, point: Point) -> bool {
+        self.used.is_some_and(|(min, max)| {
+            (min.x..=max.x).contains(&point.x)
+                && (min.y..=max.y).contains(&point.y)
+        })
+    }
+    pub fn get_tile(&self, point: Point) -> Option<&T> {
+        if !self.in_bounds(point) {
+            return None;
+        }
+        self.tiles.get_tile(point - self.origin)
+    }
+    pub fn get_tile_mut(&mut self, point: Point) -> Option<&mut T> {
+        if !self.in_bounds(point) {
+            return None;
+        }
+        self.tiles.get_tile_mut(point - self.origin)
+    }
+    /// Like `get_tile`, but anywhere outside the map is blank.
+    pub fn get_or_blank(&self, point: Point) -> &T {
+        self.get_tile(point).unwrap_or(&self.blank)
+    }
+    pub fn set_tile(&mut self, point: Point, value: T) {
+        if self.tiles.get_tile(point - self.origin).is_none() {
+            self.grow_to(point);
+        }
+        self.used = Some(match self.used {
+            None => (point, point),
+            Some((min, max)) => (
+                Point {
+                    x: min.x.min(point.x),
+                    y: min.y.min(point.y),
+                },
+                Point {
+                    x: max.x.max(point.x),
+                    y: max.y.max(point.y),
+                },
+            ),
+        });
+        self.tiles.set_tile(point - self.origin, value);
+    }
+    /// Reallocates so that `point` fits. Each side that has to move at least
+    /// doubles how far it reaches, so drawing a long line doesn't copy the
+    /// whole map every step.
+    fn grow_to(&mut self, point: Point) {
+        let width = self.tiles.get_width();
+        let height = self.tiles.get_height();
+        let mut min = self.origin;
+        let mut max = self.origin
+            + Point {
+                x: width - 1,
+                y: height - 1,
+            };
+        if width == 0 || height == 0 {
+            min = point;
+            max = point;
+        }
+        if point.x < min.x {
+            min.x = point.x.min(min.x - width);
+        }
+        if point.x > max.x {
+            max.x = point.x.max(max.x + width);
+        }
+        if point.y < min.y {
+            min.y = point.y.min(min.y - height);
+        }
+        if point.y > max.y {
+            max.y = point.y.max(max.y + height);
+        }
+        let mut tiles = Tilemap::new_with(
+            self.blank.clone(),
+            max.x - min.x + 1,
+            max.y - min.y + 1,
+        );
+        let offset = self.origin - min;
+        for y in 0..height {
+            let row = self.tiles.get_row(y).unwrap();
+            let dest = tiles.get_row_mut(y + offset.y).unwrap();
+            let start = offset.x as usize;
+            dest[start..start + row.len()].clone_from_slice(row);
+        }
+        self.tiles = tiles;
+        self.origin = min;
+    }
+    /// The smallest and largest corners of everything that's been set, or
+    /// `None` if nothing has.
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        self.used
+    }
+    /// Every tile in `bounds`, along with where it is.
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        let (min, max) = self
+            .used
+            .unwrap_or((Point { x: 0, y: 0 }, Point { x: -1, y: -1 }));
+        (min.y..=max.y).flat_map(move |y| {
+            (min.x..=max.x).map(move |x| {
+                let point = Point { x, y };
+                (point, self.get_or_blank(point))
+            })
+        })
+    }
+    /// Crops the map down to `bounds` and returns it as an ordinary
+    /// `Tilemap`, along with where its `(0, 0)` used to be.
+    pub fn into_tilemap(self) -> (Point, Tilemap<T>) {
+        let Some((min, max)) = self.used else {
+            return (Point { x: 0, y: 0 }, Tilemap::new_empty());
+        };
+        if min == self.origin
+            && max - min
+                == (Point {
+                    x: self.tiles.get_width() - 1,
+                    y: self.tiles.get_height() - 1,
+                })
+        {
+            return (min, self.tiles);
+        }
+        let mut ret = Tilemap::new_empty();
+        let start = (min.x - self.origin.x) as usize;
+        let end = (max.x - self.origin.x) as usize;
+        for y in min.y..=max.y {
+            let row = self.tiles.get_row(y - self.origin.y).unwrap();
+            ret.add_row(&row[start..=end]);
+        }
+        (min, ret)
+    }
+}
+
+/// A grid with tiles only where you've put them, for when the interesting
+/// bits are far apart. The methods are named like `GrowingTilemap`'s, but
+/// only tiles that have been set count as being there: `get_tile` is `None`
+/// for anything else, even inside `bounds`, and `iter` skips the blanks.
+#[derive(Clone, Debug)]
+pub struct SparseGrid<T> {
+    tiles: HashMap<Point, T>,
+    blank: T,
+}
+
+impl<T: Clone> SparseGrid<T> {
+    pub fn new(blank: T) -> SparseGrid<T> {
+        SparseGrid {
+            tiles: HashMap::new(),
+            blank,
+        }
+    }
+    pub fn get_tile(&self, point: Point) -> Option<&T> {
+        self.tiles.get(&point)
+    }
+    pub fn get_tile_mut(&mut self, point: Point) -> Option<&mut T> {
+        self.tiles.get_mut(&point)
+    }
+    pub fn get_or_blank(&self, point: Point) -> &T {
+        self.get_tile(point).unwrap_or(&self.blank)
+    }
+    pub fn set_tile(&mut self, point: Point, value: T) {
+        self.tiles.insert(point, value);
+    }
+    /// Makes a tile blank again, handing back what was there.
+    pub fn remove_tile(&mut self, point: Point) -> Option<T> {
+        self.tiles.remove(&point)
+    }
+    /// How many tiles have been set.
+    pub fn len(&self) -> usize {
+        self.tiles.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.tiles.is_empty()
+    }
+    pub fn bounds(&self) -> Option<(Point, Point)> {
+        let mut points = self.tiles.keys();
+        let first = *points.next()?;
+        Some(points.fold((first, first), |(min, max), point| {
+            (
+                Point {
+                    x: min.x.min(point.x),
+                    y: min.y.min(point.y),
+                },
+                Point {
+                    x: max.x.max(point.x),
+                    y: max.y.max(point.y),
+                },
+            )
+        }))
+    }
+    /// Every tile that's been set, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &T)> {
+        self.tiles.iter().map(|(point, tile)| (*point, tile))
+    }
+    /// Fills in the blanks and returns an ordinary `Tilemap` covering
+    /// `bounds`, along with where its `(0, 0)` used to be.
+    pub fn into_tilemap(self) -> (Point, Tilemap<T>) {
+        let Some((min, max)) = self.bounds() else {
+            return (Point { x: 0, y: 0 }, Tilemap::new_empty());
+        };
+        let mut ret = Tilemap::new_with(
+            self.blank,
+            max.x - min.x + 1,
+            max.y - min.y + 1,
+        );
+        for (point, tile) in self.tiles {
+            ret.set_tile(point - min, tile);
+        }
+        (min, ret)
+    }
+}
+
+/// A finite map repeated forever in every direction, without actually
+/// making any copies.
+#[derive(Copy, Clone, Debug)]
+pub struct TiledView<'a, T: Clone> {
+    map: &'a Tilemap<T>,
+}
+
+impl<'a, T: Clone> TiledView<'a, T> {
+    pub fn new(map: &'a Tilemap<T>) -> TiledView<'a, T> {
+        assert!(
+            map.get_width() > 0 && map.get_height() > 0,
+            "Can't tile an empty map"
+        );
+        TiledView { map }
+    }
+    /// Always `Some`, since the plane has no edges. (It's an `Option` to
+    /// match the other grids.)
+    pub fn get_tile(&self, point: Point) -> Option<&'a T> {
+        self.map.get_tile(self.wrap(point))
+    }
+    /// Where in the original map this point is a copy of.
+    pub fn wrap(&self, point: Point) -> Point {
+        Point {
+            x: point.x.rem_euclid(self.map.get_width()),
+            y: point.y.rem_euclid(self.map.get_height()),
+        }
+    }
+    /// Which copy of the map this point is in. The original is `(0, 0)`.
+    pub fn copy_of(&self, point: Point) -> Point {
+        Point {
+            x: point.x.div_euclid(self.map.get_width()),
+            y: point.y.div_euclid(self.map.get_height()),
+        }
+    }
+    /// Cuts a `width`×`height` window out of the plane, with its top left
+    /// corner at `min`.
+    pub fn window(&self, min: Point, width: i32, height: i32) -> Tilemap<T> {
+        let mut ret = Tilemap::new_empty();
+        let mut buf = Vec::with_capacity(width as usize);
+        for y in min.y..min.y + height {
+            buf.clear();
+            buf.extend(
+                (min.x..min.x + width)
+                    .map(|x| self.get_tile(Point { x, y }).unwrap().clone()),
+            );
+            ret.add_row(&buf);
+        }
+        ret
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn growing() {
+        let mut map = GrowingTilemap::new('.');
+        let mut pos = Point { x: 0, y: 0 };
+        for dir in "RRRDDLLLLLUUUUU".chars() {
+            pos = pos + crate::Direction::from_char(dir).unwrap();
+            map.set_tile(pos, '#');
+        }
+        assert_eq!(
+            map.bounds(),
+            Some((Point { x: -2, y: -3 }, Point { x: 3, y: 2 }))
+        );
+        assert_eq!(map.get_or_blank(Point { x: 0, y: 0 }), &'.');
+        assert_eq!(map.get_tile(Point { x: 0, y: 0 }), Some(&'.'));
+        assert_eq!(map.get_tile(Point { x: -2, y: -3 }), Some(&'#'));
+        // There's room out here after all that doubling, but it's still
+        // outside the map.
+        assert!(map
+            .tiles
+            .get_tile(Point { x: 4, y: 0 } - map.origin)
+            .is_some());
+        assert_eq!(map.get_tile(Point { x: 4, y: 0 }), None);
+        assert_eq!(map.get_tile_mut(Point { x: -3, y: 0 }), None);
+        assert_eq!(map.get_or_blank(Point { x: 4, y: 0 }), &'.');
+        let (origin, tilemap) = map.into_tilemap();
+        assert_eq!(origin, Point { x: -2, y: -3 });
+        assert_eq!(
+            tilemap.to_string(),
+            "#.....\n#.....\n#.....\n#..###\n#....#\n######\n"
+        );
+    }
+
+    #[test]
+    fn sparse_matches_growing() {
+        let points = [(5, 5), (-100, 3), (7, -40), (5, 5)];
+        let mut sparse = SparseGrid::new(0);
+        let mut growing = GrowingTilemap::new(0);
+        for (index, (x, y)) in points.into_iter().enumerate() {
+            sparse.set_tile(Point { x, y }, index + 1);
+            growing.set_tile(Point { x, y }, index + 1);
+        }
+        assert_eq!(sparse.len(), 3);
+        assert_eq!(sparse.bounds(), growing.bounds());
+        assert_eq!(sparse.into_tilemap(), growing.into_tilemap());
+    }
+
+    #[test]
+    fn tiled() {
+        let mut map = Tilemap::new_empty();
+        map.add_row(&['a', 'b']);
+        map.add_row(&['c', 'd']);
+        let view = TiledView::new(&map);
+        assert_eq!(view.get_tile(Point { x: -1, y: -1 }), Some(&'d'));
+        assert_eq!(view.copy_of(Point { x: -1, y: 2 }), Point { x: -1, y: 1 });
+        let window = view.window(Point { x: 1, y: 0 }, 3, 2);
+        assert_eq!(window.to_string(), "bab\ndcd\n");
+    }
+}
@@ -0,0 +1,214 @@
+use std::path::Path;
+
+use crate::{Point, Tilemap};
+
+/// An RGB pixel.
+pub type Rgb = [u8; 3];
+
+/// A picture is just a map of pixels.
+pub type Pixels = Tilemap<Rgb>;
+
+impl<T: Clone> Tilemap<T> {
+    /// Turns each tile into a `scale`×`scale` square of the color `f` picks
+    /// for it.
+    pub fn to_pixels(&self, f: impl Fn(&T) -> Rgb, scale: i32) -> Pixels {
+        assert!(scale > 0, "scale must be positive, not {scale}");
+        let mut ret = Tilemap::new_empty();
+        let mut buf = Vec::with_capacity((self.get_width() * scale) as usize);
+        for row in self.rows() {
+            buf.clear();
+            for tile in row.iter() {
+                let color = f(tile);
+                buf.extend(std::iter::repeat_n(color, scale as usize));
+            }
+            for _ in 0..scale {
+                ret.add_row(&buf);
+            }
+        }
+        ret
+    }
+    /// Writes the map out as a picture; see `to_pixels` and `write_pixels`.
+    pub fn write_image(
+        &self,
+        path: impl AsRef<Path>,
+        f: impl Fn(&T) -> Rgb,
+        scale: i32,
+    ) -> std::io::Result<()> {
+        write_pixels(path, &self.to_pixels(f, scale))
+    }
+}
+
+/// Writes a PNG if the path ends in `.png`, and a binary PPM otherwise.
+pub fn write_pixels(
+    path: impl AsRef<Path>,
+    pixels: &Pixels,
+) -> std::io::Result<()> {
+    let path = path.as_ref();
+    let is_png = path
+        .extension()
+        .is_some_and(|x| x.eq_ignore_ascii_case("png"));
+    let bytes = if is_png {
+        encode_png(pixels)
+    } else {
+        encode_ppm(pixels)
+    };
+    std::fs::write(path, bytes)
+}
+
+pub fn encode_ppm(pixels: &Pixels) -> Vec<u8> {
+    let mut out =
+        format!("P6\n{} {}\n255\n", pixels.get_width(), pixels.get_height())
+            .into_bytes();
+    out.extend(pixels.iter().flatten());
+    out
+}
+
+/// Encodes an 8-bit RGB PNG. There's no compression (the image data goes in
+/// "stored" deflate blocks), which keeps this short and dependency-free at
+/// the cost of files about as big as the PPM would be.
+pub fn encode_png(pixels: &Pixels) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(
+        (pixels.get_width() * 3 + 1) as usize * pixels.get_height() as usize,
+    );
+    for row in pixels.rows() {
+        // Filter type 0: none
+        raw.push(0);
+        raw.extend(row.iter().flatten());
+    }
+    let mut zlib = vec![0x78, 0x01];
+    let mut chunks = raw.chunks(65535).peekable();
+    if chunks.peek().is_none() {
+        zlib.extend([1, 0, 0, 0xFF, 0xFF]);
+    }
+    while let Some(chunk) = chunks.next() {
+        zlib.push(chunks.peek().is_none() as u8);
+        let len = chunk.len() as u16;
+        zlib.extend(len.to_le_bytes());
+        zlib.extend((!len).to_le_bytes());
+        zlib.extend_from_slice(chunk);
+    }
+    zlib.extend(adler32(&raw).to_be_bytes());
+    let mut header = vec![];
+    header.extend((pixels.get_width() as u32).to_be_bytes());
+    header.extend((pixels.get_height() as u32).to_be_bytes());
+    // 8 bits per channel, RGB, default compression/filter, no interlace
+    header.extend([8, 2, 0, 0, 0]);
+    let mut out = b"\x89PNG\r\n\x1A\n".to_vec();
+    for (kind, data) in
+        [(b"IHDR", &header[..]), (b"IDAT", &zlib[..]), (b"IEND", &[])]
+    {
+        out.extend((data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(data);
+        let crc = crc32(&out[start..]);
+        out.extend(crc.to_be_bytes());
+    }
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = (crc >> 1) ^ (0xEDB88320 & (crc & 1).wrapping_neg());
+        }
+    }
+    !crc
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for chunk in data.chunks(5552) {
+        for byte in chunk {
+            a += *byte as u32;
+            b += a;
+        }
+        a %= 65521;
+        b %= 65521;
+    }
+    (b << 16) | a
+}
+
+/// Lays pictures out in a grid, left to right and then top to bottom, with
+/// `gap` pixels of `background` between them. Every cell of the grid is as
+/// big as the biggest picture, and smaller ones sit in its top left corner.
+pub fn sprite_sheet(
+    frames: &[Pixels],
+    columns: usize,
+    gap: i32,
+    background: Rgb,
+) -> Pixels {
+    assert!(columns > 0, "a sprite sheet needs at least one column");
+    let cell_width = frames.iter().map(|x| x.get_width()).max().unwrap_or(0);
+    let cell_height = frames.iter().map(|x| x.get_height()).max().unwrap_or(0);
+    let columns = columns.min(frames.len()).max(1) as i32;
+    let rows = frames.len().div_ceil(columns as usize) as i32;
+    let mut sheet = Tilemap::new_with(
+        background,
+        (cell_width + gap) * columns - gap,
+        ((cell_height + gap) * rows - gap).max(0),
+    );
+    for (index, frame) in frames.iter().enumerate() {
+        let corner = Point {
+            x: (index as i32 % columns) * (cell_width + gap),
+            y: (index as i32 / columns) * (cell_height + gap),
+        };
+        for (y, row) in frame.rows().enumerate() {
+            let dest = sheet.get_row_mut(corner.y + y as i32).unwrap();
+            let start = corner.x as usize;
+            dest[start..start + row.len()].copy_from_slice(row);
+        }
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"IEND"), 0xAE426082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E60398);
+    }
+
+    #[test]
+    fn scaled_ppm() {
+        let mut map = Tilemap::new_empty();
+        map.add_row(&[false, true]);
+        let pixels = map.to_pixels(|x| [*x as u8 * 255; 3], 2);
+        assert_eq!(pixels.get_width(), 4);
+        assert_eq!(pixels.get_height(), 2);
+        let ppm = encode_ppm(&pixels);
+        assert!(ppm.starts_with(b"P6\n4 2\n255\n"));
+        // The last row is two black pixels and then two white ones.
+        assert_eq!(&ppm[ppm.len() - 12..ppm.len() - 6], &[0; 6]);
+        assert_eq!(&ppm[ppm.len() - 6..], &[255; 6]);
+    }
+
+    #[test]
+    fn png_structure() {
+        let pixels = Tilemap::new_with([1, 2, 3], 3, 2);
+        let png = encode_png(&pixels);
+        assert!(png.starts_with(b"\x89PNG\r\n\x1A\n\0\0\0\x0DIHDR"));
+        assert!(png.ends_with(b"IEND\xAE\x42\x60\x82"));
+        // Two rows of a filter byte and three pixels, stored uncompressed
+        let idat = png.windows(4).position(|x| x == b"IDAT").unwrap();
+        assert_eq!(&png[idat + 4..idat + 9], &[0x78, 0x01, 1, 20, 0]);
+        assert_eq!(&png[idat + 11..idat + 15], &[0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn sheet() {
+        let red = Tilemap::new_with([255, 0, 0], 2, 2);
+        let blue = Tilemap::new_with([0, 0, 255], 1, 1);
+        let sheet = sprite_sheet(&[red.clone(), blue, red], 2, 1, [0; 3]);
+        assert_eq!(sheet.get_width(), 5);
+        assert_eq!(sheet.get_height(), 5);
+        assert_eq!(sheet.get_tile(Point { x: 3, y: 0 }), Some(&[0, 0, 255]));
+        assert_eq!(sheet.get_tile(Point { x: 4, y: 0 }), Some(&[0, 0, 0]));
+        assert_eq!(sheet.get_tile(Point { x: 1, y: 4 }), Some(&[255, 0, 0]));
+    }
+}
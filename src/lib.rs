@@ -2,16 +2,35 @@ use std::{
     fmt::{Display, Formatter, Result as FmtResult},
     hash::{Hash, Hasher},
     ops::{Add, Mul, Neg, Sub},
+    str::FromStr,
 };
 
+use anyhow::{anyhow, bail};
+use num::{PrimInt, Signed};
+
+pub mod animate;
+pub mod bitgrid;
+pub mod cards;
+pub mod golly;
+pub mod grid;
+pub mod image;
+pub mod polygon;
+pub mod render;
+pub mod tiling;
+pub mod vec3;
+
+pub use vec3::{Axis, Vec3};
+
+/// A point on a grid. Coordinates are `i32` unless you ask for something
+/// wider, which the days with huge distances do.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Point {
-    pub x: i32,
-    pub y: i32,
+pub struct Point<T = i32> {
+    pub x: T,
+    pub y: T,
 }
 
-impl Neg for Point {
-    type Output = Point;
+impl<T: PrimInt + Signed> Neg for Point<T> {
+    type Output = Point<T>;
     fn neg(self) -> Self::Output {
         Point {
             x: -self.x,
@@ -20,8 +39,8 @@ impl Neg for Point {
     }
 }
 
-impl Add for Point {
-    type Output = Point;
+impl<T: PrimInt> Add for Point<T> {
+    type Output = Point<T>;
     fn add(self, rhs: Self) -> Self::Output {
         Point {
             x: self.x + rhs.x,
@@ -30,8 +49,8 @@ impl Add for Point {
     }
 }
 
-impl Sub for Point {
-    type Output = Point;
+impl<T: PrimInt> Sub for Point<T> {
+    type Output = Point<T>;
     fn sub(self, rhs: Self) -> Self::Output {
         Point {
             x: self.x - rhs.x,
@@ -40,31 +59,23 @@ impl Sub for Point {
     }
 }
 
-impl Add<Direction> for Point {
-    type Output = Point;
+impl<T: PrimInt + Signed> Add<Direction> for Point<T> {
+    type Output = Point<T>;
     fn add(self, rhs: Direction) -> Self::Output {
-        let rhs: Point = rhs.into();
-        Point {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-        }
+        self + Point::from(rhs)
     }
 }
 
-impl Sub<Direction> for Point {
-    type Output = Point;
+impl<T: PrimInt + Signed> Sub<Direction> for Point<T> {
+    type Output = Point<T>;
     fn sub(self, rhs: Direction) -> Self::Output {
-        let rhs: Point = rhs.into();
-        Point {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-        }
+        self - Point::from(rhs)
     }
 }
 
-impl Mul<i32> for Point {
-    type Output = Point;
-    fn mul(self, rhs: i32) -> Self::Output {
+impl<T: PrimInt> Mul<T> for Point<T> {
+    type Output = Point<T>;
+    fn mul(self, rhs: T) -> Self::Output {
         Point {
             x: self.x * rhs,
             y: self.y * rhs,
@@ -72,6 +83,56 @@ impl Mul<i32> for Point {
     }
 }
 
+impl<T: PrimInt> Point<T> {
+    /// `None` if either coordinate overflows.
+    pub fn checked_add(self, rhs: Point<T>) -> Option<Point<T>> {
+        Some(Point {
+            x: self.x.checked_add(&rhs.x)?,
+            y: self.y.checked_add(&rhs.y)?,
+        })
+    }
+    /// `None` if either coordinate overflows.
+    pub fn checked_sub(self, rhs: Point<T>) -> Option<Point<T>> {
+        Some(Point {
+            x: self.x.checked_sub(&rhs.x)?,
+            y: self.y.checked_sub(&rhs.y)?,
+        })
+    }
+    /// `None` if either coordinate overflows.
+    pub fn checked_mul(self, rhs: T) -> Option<Point<T>> {
+        Some(Point {
+            x: self.x.checked_mul(&rhs)?,
+            y: self.y.checked_mul(&rhs)?,
+        })
+    }
+    /// The same point with a different coordinate type, or `None` if it
+    /// doesn't fit.
+    pub fn try_cast<U: PrimInt>(self) -> Option<Point<U>> {
+        Some(Point {
+            x: U::from(self.x)?,
+            y: U::from(self.y)?,
+        })
+    }
+    /// The same point with a different coordinate type. Panics if it doesn't
+    /// fit, because silently wrapping a coordinate is how you spend an hour
+    /// debugging the wrong answer.
+    pub fn cast<U: PrimInt>(self) -> Point<U>
+    where
+        T: std::fmt::Debug,
+    {
+        self.try_cast().unwrap_or_else(|| {
+            panic!("{self:?} doesn't fit in {}", std::any::type_name::<U>())
+        })
+    }
+}
+
+impl<T: PrimInt + Signed> Point<T> {
+    /// Taxicab distance from the origin.
+    pub fn manhattan(&self) -> T {
+        self.x.abs() + self.y.abs()
+    }
+}
+
 impl Point {
     pub const NORTH: Point = Point { x: 0, y: -1 };
     pub const SOUTH: Point = Point { x: 0, y: 1 };
@@ -110,6 +171,20 @@ impl Direction {
             Direction::West => '←',
         }
     }
+    pub fn from_ordinal(ordinal: usize) -> Option<Direction> {
+        Direction::ALL.get(ordinal).copied()
+    }
+    /// Understands `U`/`D`/`L`/`R`, `N`/`S`/`E`/`W`, and `^`/`v`/`<`/`>`,
+    /// which between them cover every puzzle so far.
+    pub fn from_char(ch: char) -> Option<Direction> {
+        match ch {
+            'U' | 'N' | '^' => Some(Direction::North),
+            'D' | 'S' | 'v' => Some(Direction::South),
+            'R' | 'E' | '>' => Some(Direction::East),
+            'L' | 'W' | '<' => Some(Direction::West),
+            _ => None,
+        }
+    }
     pub fn rotate_cw(&self) -> Direction {
         match self {
             Direction::North => Direction::East,
@@ -118,27 +193,182 @@ impl Direction {
             Direction::West => Direction::North,
         }
     }
+    pub fn rotate_ccw(&self) -> Direction {
+        -self.rotate_cw()
+    }
+    /// Which way you're facing after turning left. (Same as `rotate_ccw`,
+    /// but reads better when you're the one walking.)
+    pub fn turn_left(&self) -> Direction {
+        self.rotate_ccw()
+    }
+    pub fn turn_right(&self) -> Direction {
+        self.rotate_cw()
+    }
+    pub fn reverse(&self) -> Direction {
+        -*self
+    }
+    /// Which way a beam heading this way goes after bouncing off a `/`
+    /// mirror.
+    pub fn reflect_foreslash(&self) -> Direction {
+        match self {
+            Direction::North => Direction::East,
+            Direction::South => Direction::West,
+            Direction::East => Direction::North,
+            Direction::West => Direction::South,
+        }
+    }
+    /// Which way a beam heading this way goes after bouncing off a `\`
+    /// mirror.
+    pub fn reflect_backslash(&self) -> Direction {
+        -self.reflect_foreslash()
+    }
 }
 
-impl From<Point> for Direction {
-    fn from(value: Point) -> Self {
+/// The four cardinal directions and the four diagonals between them, in
+/// clockwise order starting from north.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Direction8 {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction8 {
+    pub const ALL: &[Direction8] = &[
+        Direction8::North,
+        Direction8::NorthEast,
+        Direction8::East,
+        Direction8::SouthEast,
+        Direction8::South,
+        Direction8::SouthWest,
+        Direction8::West,
+        Direction8::NorthWest,
+    ];
+    pub fn to_ordinal(&self) -> usize {
+        *self as usize
+    }
+    pub fn from_ordinal(ordinal: usize) -> Option<Direction8> {
+        Direction8::ALL.get(ordinal).copied()
+    }
+    pub fn to_arrow(&self) -> char {
+        ['↑', '↗', '→', '↘', '↓', '↙', '←', '↖'][self.to_ordinal()]
+    }
+    /// An eighth of a turn clockwise.
+    pub fn rotate_cw(&self) -> Direction8 {
+        Direction8::ALL[(self.to_ordinal() + 1) % 8]
+    }
+    /// An eighth of a turn counterclockwise.
+    pub fn rotate_ccw(&self) -> Direction8 {
+        Direction8::ALL[(self.to_ordinal() + 7) % 8]
+    }
+    pub fn reverse(&self) -> Direction8 {
+        Direction8::ALL[(self.to_ordinal() + 4) % 8]
+    }
+    pub fn is_diagonal(&self) -> bool {
+        self.to_ordinal() % 2 == 1
+    }
+}
+
+impl From<Direction> for Direction8 {
+    fn from(value: Direction) -> Self {
         match value {
-            Point::NORTH => Direction::North,
-            Point::SOUTH => Direction::South,
-            Point::EAST => Direction::East,
-            Point::WEST => Direction::West,
-            _ => panic!("cannot make a Direction from a non-orthogonal Point"),
+            Direction::North => Direction8::North,
+            Direction::South => Direction8::South,
+            Direction::East => Direction8::East,
+            Direction::West => Direction8::West,
         }
     }
 }
 
-impl From<Direction> for Point {
+impl TryFrom<Direction8> for Direction {
+    type Error = anyhow::Error;
+    fn try_from(value: Direction8) -> anyhow::Result<Direction> {
+        Direction::ALL
+            .iter()
+            .copied()
+            .find(|dir| Direction8::from(*dir) == value)
+            .ok_or(anyhow!("{value:?} is a diagonal"))
+    }
+}
+
+impl<T: PrimInt + Signed> From<Direction8> for Point<T> {
+    fn from(value: Direction8) -> Self {
+        let (zero, one) = (T::zero(), T::one());
+        let (x, y) = match value {
+            Direction8::North => (zero, -one),
+            Direction8::NorthEast => (one, -one),
+            Direction8::East => (one, zero),
+            Direction8::SouthEast => (one, one),
+            Direction8::South => (zero, one),
+            Direction8::SouthWest => (-one, one),
+            Direction8::West => (-one, zero),
+            Direction8::NorthWest => (-one, -one),
+        };
+        Point { x, y }
+    }
+}
+
+impl<T: PrimInt + Signed> TryFrom<Point<T>> for Direction8 {
+    type Error = anyhow::Error;
+    fn try_from(value: Point<T>) -> anyhow::Result<Direction8> {
+        Direction8::ALL
+            .iter()
+            .copied()
+            .find(|dir| Point::from(*dir) == value)
+            .ok_or(anyhow!("not a one-step Point"))
+    }
+}
+
+impl<T: PrimInt + Signed> Add<Direction8> for Point<T> {
+    type Output = Point<T>;
+    fn add(self, rhs: Direction8) -> Self::Output {
+        self + Point::from(rhs)
+    }
+}
+
+impl<T: PrimInt + Signed> Mul<T> for Direction8 {
+    type Output = Point<T>;
+    fn mul(self, rhs: T) -> Point<T> {
+        Point::from(self) * rhs
+    }
+}
+
+impl<T: PrimInt + Signed> TryFrom<Point<T>> for Direction {
+    type Error = anyhow::Error;
+    fn try_from(value: Point<T>) -> anyhow::Result<Direction> {
+        Direction::ALL
+            .iter()
+            .copied()
+            .find(|dir| Point::from(*dir) == value)
+            .ok_or(anyhow!("not a one-step orthogonal Point"))
+    }
+}
+
+impl FromStr for Direction {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Direction> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(ch), None) => Direction::from_char(ch)
+                .ok_or(anyhow!("{ch:?} isn't a direction")),
+            _ => bail!("{s:?} isn't a direction"),
+        }
+    }
+}
+
+impl<T: PrimInt + Signed> From<Direction> for Point<T> {
     fn from(value: Direction) -> Self {
+        let (zero, one) = (T::zero(), T::one());
         match value {
-            Direction::North => Point::NORTH,
-            Direction::South => Point::SOUTH,
-            Direction::East => Point::EAST,
-            Direction::West => Point::WEST,
+            Direction::North => Point { x: zero, y: -one },
+            Direction::South => Point { x: zero, y: one },
+            Direction::East => Point { x: one, y: zero },
+            Direction::West => Point { x: -one, y: zero },
         }
     }
 }
@@ -155,14 +385,14 @@ impl Neg for Direction {
     }
 }
 
-impl Mul<i32> for Direction {
-    type Output = Point;
-    fn mul(self, rhs: i32) -> Point {
+impl<T: PrimInt + Signed> Mul<T> for Direction {
+    type Output = Point<T>;
+    fn mul(self, rhs: T) -> Point<T> {
         Point::from(self) * rhs
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Tilemap<T: Clone> {
     vec: Vec<T>,
     width: i32,
@@ -247,7 +477,7 @@ impl<T: Clone> Tilemap<T> {
     }
     pub fn find_tiles<'a>(
         &'a self,
-        predicate: impl 'a + Clone + Copy + Fn(&T) -> bool,
+        predicate: impl 'a + Copy + Fn(&T) -> bool,
     ) -> impl 'a + Iterator<Item = Point> {
         self.rows().enumerate().flat_map(move |(y, row)| {
             row.iter().enumerate().filter_map(move |(x, tile)| {
@@ -341,6 +571,13 @@ impl<T: Clone> Tilemap<T> {
     pub fn rotate_cw(&self) -> Tilemap<T> {
         self.transpose().flip_h()
     }
+    pub fn map<U: Clone>(&self, f: impl FnMut(&T) -> U) -> Tilemap<U> {
+        Tilemap {
+            vec: self.vec.iter().map(f).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
 }
 
 impl<T: Clone + Display> Display for Tilemap<T> {
@@ -403,145 +640,73 @@ impl<T: Clone + PartialEq> PartialEq for Tilemap<T> {
 
 impl<T: Clone + Eq> Eq for Tilemap<T> {}
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
-pub struct Point3 {
-    pub x: i32,
-    pub y: i32,
-    pub z: i32,
-}
-
-impl Neg for Point3 {
-    type Output = Point3;
-    fn neg(self) -> Self::Output {
-        Point3 {
-            x: -self.x,
-            y: -self.y,
-            z: -self.z,
-        }
-    }
-}
-
-impl Add for Point3 {
-    type Output = Point3;
-    fn add(self, rhs: Self) -> Self::Output {
-        Point3 {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
-    }
-}
-
-impl Sub for Point3 {
-    type Output = Point3;
-    fn sub(self, rhs: Self) -> Self::Output {
-        Point3 {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
-        }
-    }
-}
-
-impl Mul<i32> for Point3 {
-    type Output = Point3;
-    fn mul(self, rhs: i32) -> Self::Output {
-        Point3 {
-            x: self.x * rhs,
-            y: self.y * rhs,
-            z: self.z * rhs,
-        }
-    }
-}
-
-impl Point3 {
-    /// Returns this point, but with each coordinate replaced with -1, 0, or 1
-    /// according to its previous sign.
-    pub fn unit(&self) -> Point3 {
-        Point3 {
-            x: self.x.signum(),
-            y: self.y.signum(),
-            z: self.z.signum(),
-        }
-    }
-    pub fn from_str_or_panic(s: &str) -> Point3 {
-        let mut split = s.split(',');
-        let x = split.next().unwrap();
-        let y = split.next().unwrap();
-        let z = split.next().unwrap();
-        assert!(split.next().is_none());
-        Point3 {
-            x: x.parse().unwrap(),
-            y: y.parse().unwrap(),
-            z: z.parse().unwrap(),
-        }
-    }
-}
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
-pub struct Point3f {
-    pub x: f64,
-    pub y: f64,
-    pub z: f64,
-}
+/// A point in 3-D space with integer coordinates.
+pub type Point3 = Vec3<i32>;
 
-impl Neg for Point3f {
-    type Output = Point3f;
-    fn neg(self) -> Self::Output {
-        Point3f {
-            x: -self.x,
-            y: -self.y,
-            z: -self.z,
-        }
-    }
-}
+/// A point in 3-D space with floating-point coordinates.
+pub type Point3f = Vec3<f64>;
 
-impl Add for Point3f {
-    type Output = Point3f;
-    fn add(self, rhs: Self) -> Self::Output {
-        Point3f {
-            x: self.x + rhs.x,
-            y: self.y + rhs.y,
-            z: self.z + rhs.z,
-        }
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-impl Sub for Point3f {
-    type Output = Point3f;
-    fn sub(self, rhs: Self) -> Self::Output {
-        Point3f {
-            x: self.x - rhs.x,
-            y: self.y - rhs.y,
-            z: self.z - rhs.z,
+    #[test]
+    fn wide_points() {
+        let far: Point<i64> = Direction::West * 3_000_000_000;
+        assert_eq!(
+            far,
+            Point {
+                x: -3_000_000_000,
+                y: 0
+            }
+        );
+        assert_eq!(far.try_cast::<i32>(), None);
+        assert_eq!(far.checked_mul(4_000_000_000), None);
+        assert_eq!(far.checked_add(far), Some(far * 2));
+        let near = Point { x: 3, y: -4 };
+        assert_eq!(near.cast::<i128>().manhattan(), 7);
+    }
+
+    #[test]
+    fn turning() {
+        for dir in Direction::ALL.iter() {
+            assert_eq!(dir.turn_left().turn_right(), *dir);
+            assert_eq!(dir.reflect_foreslash().reflect_foreslash(), *dir);
+            assert_eq!(
+                dir.reflect_backslash(),
+                dir.reflect_foreslash().reverse()
+            );
+            assert_eq!(
+                Direction::try_from(Point::<i32>::from(*dir)).ok(),
+                Some(*dir)
+            );
         }
+        assert_eq!(Direction::East.reflect_foreslash(), Direction::North);
+        assert_eq!(Direction::East.reflect_backslash(), Direction::South);
+        assert!(Direction::try_from(Point { x: 1, y: 1 }).is_err());
+        assert_eq!(
+            "v<R".chars().map(Direction::from_char).collect::<Vec<_>>(),
+            [Direction::South, Direction::West, Direction::East].map(Some)
+        );
     }
-}
 
-impl Mul<f64> for Point3f {
-    type Output = Point3f;
-    fn mul(self, rhs: f64) -> Self::Output {
-        Point3f {
-            x: self.x * rhs,
-            y: self.y * rhs,
-            z: self.z * rhs,
-        }
+    #[test]
+    fn eight_ways() {
+        let diagonal = Point { x: 1, y: 1 };
+        assert_eq!(Direction8::try_from(diagonal).unwrap().to_arrow(), '↘');
+        assert_eq!(
+            Direction8::North.rotate_cw().rotate_cw(),
+            Direction8::from(Direction::East)
+        );
+        assert_eq!(Direction8::NorthWest.rotate_cw(), Direction8::North);
+        assert_eq!(Direction8::North.rotate_ccw(), Direction8::NorthWest);
+        assert_eq!(Direction8::SouthWest.reverse(), Direction8::NorthEast);
+        assert!(Direction::try_from(Direction8::SouthWest).is_err());
     }
-}
 
-impl Point3f {
-    pub fn from_str_or_panic(s: &str) -> Point3f {
-        let mut split = s.split(',');
-        let x = split.next().unwrap();
-        let y = split.next().unwrap();
-        let z = split.next().unwrap();
-        assert!(split.next().is_none());
-        Point3f {
-            x: x.parse().unwrap(),
-            y: y.parse().unwrap(),
-            z: z.parse().unwrap(),
-        }
-    }
-    pub fn magnitude_squared(&self) -> f64 {
-        self.x * self.x + self.y * self.y + self.z * self.z
+    #[test]
+    #[should_panic(expected = "doesn't fit in i32")]
+    fn narrowing_is_loud() {
+        Point::<i64> { x: 0, y: 1 << 40 }.cast::<i32>();
     }
 }
@@ -0,0 +1,123 @@
+use num::Integer;
+
+/// The things you can know about a simple polygon whose vertices all sit on
+/// integer coordinates, without ever looking at its insides.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct LatticePolygon {
+    /// Twice the enclosed area. (The area itself can end in .5.)
+    pub double_area: u128,
+    /// How many lattice points lie on the edges.
+    pub boundary: u128,
+    /// How many lattice points lie strictly inside.
+    pub interior: u128,
+}
+
+impl LatticePolygon {
+    /// Measures the polygon with the given vertices, in order. Either winding
+    /// works, the last vertex connects back to the first, and repeated
+    /// vertices or extra ones partway along an edge are fine. The edges
+    /// mustn't cross or double back over each other, though; that counts
+    /// boundary points twice and panics if Pick's theorem goes negative.
+    ///
+    /// Area comes from the shoelace formula, boundary points from the GCD of
+    /// each edge's extents, and interior points from Pick's theorem.
+    pub fn new(
+        vertices: impl IntoIterator<Item = (i64, i64)>,
+    ) -> LatticePolygon {
+        let mut vertices = vertices.into_iter();
+        let Some(first) = vertices.next() else {
+            return LatticePolygon {
+                double_area: 0,
+                boundary: 0,
+                interior: 0,
+            };
+        };
+        let mut signed_double_area: i128 = 0;
+        let mut boundary: u128 = 0;
+        let mut prev = first;
+        for next in vertices.chain(std::iter::once(first)) {
+            signed_double_area += prev.0 as i128 * next.1 as i128
+                - next.0 as i128 * prev.1 as i128;
+            let dx = (next.0 as i128 - prev.0 as i128).unsigned_abs();
+            let dy = (next.1 as i128 - prev.1 as i128).unsigned_abs();
+            boundary += dx.gcd(&dy);
+            prev = next;
+        }
+        let double_area = signed_double_area.unsigned_abs();
+        // Pick: A = I + B/2 - 1, so 2I = 2A - B + 2
+        let interior = if double_area == 0 {
+            0
+        } else {
+            (double_area + 2)
+                .checked_sub(boundary)
+                .expect("edges overlap, so this isn't a simple polygon")
+                / 2
+        };
+        LatticePolygon {
+            double_area,
+            boundary,
+            interior,
+        }
+    }
+    /// Every lattice point on or inside the polygon. This is the number of
+    /// tiles covered if each vertex is the middle of a tile.
+    pub fn total_points(&self) -> u128 {
+        self.interior + self.boundary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn square_either_winding() {
+        let square = [(0, 0), (4, 0), (4, 4), (0, 4)];
+        let cw = LatticePolygon::new(square);
+        let ccw = LatticePolygon::new(square.into_iter().rev());
+        assert_eq!(cw, ccw);
+        assert_eq!(cw.double_area, 32);
+        assert_eq!(cw.boundary, 16);
+        assert_eq!(cw.interior, 9);
+        assert_eq!(cw.total_points(), 25);
+    }
+
+    #[test]
+    fn diagonal_edges() {
+        let triangle = LatticePolygon::new([(0, 0), (6, 0), (0, 3)]);
+        assert_eq!(triangle.double_area, 18);
+        // 6 + 3 + gcd(6, 3)
+        assert_eq!(triangle.boundary, 12);
+        assert_eq!(triangle.interior, 4);
+    }
+
+    #[test]
+    fn extra_vertices() {
+        let square = [(0, 0), (2, 0), (4, 0), (4, 4), (4, 4), (0, 4)];
+        assert_eq!(
+            LatticePolygon::new(square),
+            LatticePolygon::new([(0, 0), (4, 0), (4, 4), (0, 4)])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "simple polygon")]
+    fn doubling_back() {
+        // A spike out to (4, 10) and back along the same line.
+        LatticePolygon::new([(0, 0), (1, 0), (1, 1), (4, 10), (1, 1), (0, 1)]);
+    }
+
+    #[test]
+    fn huge_coordinates() {
+        let big = 3_000_000_000;
+        let square = LatticePolygon::new([
+            (-big, -big),
+            (big, -big),
+            (big, big),
+            (-big, big),
+        ]);
+        let side = 2 * big as u128;
+        assert_eq!(square.double_area, 2 * side * side);
+        assert_eq!(square.total_points(), (side + 1) * (side + 1));
+    }
+}
@@ -0,0 +1,281 @@
+use std::{fmt::Write, io::IsTerminal};
+
+use crate::{Point, Tilemap};
+
+/// A terminal color: either one of the 16 classic ANSI colors (0–7 normal,
+/// 8–15 bright) or a 24-bit true color.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Color {
+    Ansi(u8),
+    Rgb([u8; 3]),
+}
+
+impl Color {
+    pub const RED: Color = Color::Ansi(1);
+    pub const YELLOW: Color = Color::Ansi(3);
+    pub const WHITE: Color = Color::Ansi(7);
+    pub const BRIGHT_WHITE: Color = Color::Ansi(15);
+
+    /// `base` is 30 for foreground and 40 for background.
+    fn write_sgr(&self, out: &mut String, base: u8) {
+        match *self {
+            Color::Ansi(n) if n < 8 => write!(out, ";{}", base + n),
+            Color::Ansi(n) => write!(out, ";{}", base + 60 + (n & 7)),
+            Color::Rgb([r, g, b]) => {
+                write!(out, ";{};2;{r};{g};{b}", base + 8)
+            }
+        }
+        .unwrap();
+    }
+}
+
+/// What to draw in one spot: a glyph and how to dress it up.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Cell {
+    pub glyph: char,
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub bold: bool,
+}
+
+impl Cell {
+    pub fn plain(glyph: char) -> Cell {
+        Cell {
+            glyph,
+            fg: None,
+            bg: None,
+            bold: false,
+        }
+    }
+    pub fn fg(self, color: Color) -> Cell {
+        Cell {
+            fg: Some(color),
+            ..self
+        }
+    }
+    pub fn bg(self, color: Color) -> Cell {
+        Cell {
+            bg: Some(color),
+            ..self
+        }
+    }
+    pub fn bold(self) -> Cell {
+        Cell { bold: true, ..self }
+    }
+    fn style(&self) -> (Option<Color>, Option<Color>, bool) {
+        (self.fg, self.bg, self.bold)
+    }
+}
+
+type Painter<'a, T> = Box<dyn 'a + Fn(Point, &T) -> Cell>;
+type Overlay<'a> = Box<dyn 'a + Fn(Point, Cell) -> Cell>;
+
+/// Draws a `Tilemap` on the terminal. Each tile becomes a `Cell` by way of
+/// a closure, and then any overlays get a chance to change it. Escape codes
+/// are left out when stdout isn't a terminal, so piping the output to a file
+/// gets you plain text.
+pub struct Renderer<'a, T: Clone> {
+    map: &'a Tilemap<T>,
+    cell: Painter<'a, T>,
+    overlays: Vec<Overlay<'a>>,
+    rulers: bool,
+    viewport: Option<(Point, Point)>,
+    color: bool,
+}
+
+impl<'a, T: Clone> Renderer<'a, T> {
+    pub fn new(
+        map: &'a Tilemap<T>,
+        cell: impl 'a + Fn(Point, &T) -> Cell,
+    ) -> Renderer<'a, T> {
+        Renderer {
+            map,
+            cell: Box::new(cell),
+            overlays: vec![],
+            rulers: false,
+            viewport: None,
+            color: std::io::stdout().is_terminal(),
+        }
+    }
+    /// Lets `f` change any cell after it's been drawn. Later overlays go on
+    /// top of earlier ones.
+    pub fn overlay(mut self, f: impl 'a + Fn(Point, Cell) -> Cell) -> Self {
+        self.overlays.push(Box::new(f));
+        self
+    }
+    /// Draws `glyph` over each point, in `color`. Handy for paths.
+    pub fn path(
+        self,
+        points: impl IntoIterator<Item = (Point, char)>,
+        color: Color,
+    ) -> Self {
+        let points: std::collections::HashMap<Point, char> =
+            points.into_iter().collect();
+        self.overlay(move |point, cell| match points.get(&point) {
+            Some(glyph) => Cell {
+                glyph: *glyph,
+                ..cell
+            }
+            .fg(color)
+            .bold(),
+            None => cell,
+        })
+    }
+    /// Gives each point a background color, leaving the glyph alone.
+    pub fn highlight(
+        self,
+        points: impl IntoIterator<Item = Point>,
+        color: Color,
+    ) -> Self {
+        let points: std::collections::HashSet<Point> =
+            points.into_iter().collect();
+        self.overlay(move |point, cell| {
+            if points.contains(&point) {
+                cell.bg(color)
+            } else {
+                cell
+            }
+        })
+    }
+    /// Marks one spot with reversed colors, so it shows up even without
+    /// any color (the glyph is swapped for `█` then).
+    pub fn cursor(self, at: Point) -> Self {
+        let color = self.color;
+        self.overlay(move |point, cell| match (point == at, color) {
+            (false, _) => cell,
+            (true, true) => Cell {
+                fg: Some(cell.bg.unwrap_or(Color::Ansi(0))),
+                bg: Some(cell.fg.unwrap_or(Color::BRIGHT_WHITE)),
+                ..cell
+            },
+            (true, false) => Cell::plain('█'),
+        })
+    }
+    /// Prints column numbers across the top and row numbers down the side.
+    pub fn rulers(mut self, rulers: bool) -> Self {
+        self.rulers = rulers;
+        self
+    }
+    /// Only draws the tiles from `min` to `max`, inclusive. Whatever part of
+    /// that is off the map is left out.
+    pub fn viewport(mut self, min: Point, max: Point) -> Self {
+        self.viewport = Some((min, max));
+        self
+    }
+    /// Overrides the guess about whether to use escape codes.
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+    pub fn render(&self) -> String {
+        let (mut min, mut max) = self.viewport.unwrap_or((
+            Point { x: 0, y: 0 },
+            Point {
+                x: i32::MAX,
+                y: i32::MAX,
+            },
+        ));
+        min.x = min.x.max(0);
+        min.y = min.y.max(0);
+        max.x = max.x.min(self.map.get_width() - 1);
+        max.y = max.y.min(self.map.get_height() - 1);
+        let mut out = String::new();
+        let margin = if self.rulers {
+            max.y.max(0).to_string().len().max(min.y.to_string().len()) + 1
+        } else {
+            0
+        };
+        if self.rulers && max.x >= min.x {
+            let width = max.x.to_string().len() as u32;
+            for place in (0..width).rev() {
+                out.push_str(&" ".repeat(margin));
+                for x in min.x..=max.x {
+                    let digits = x / 10i32.pow(place);
+                    if digits == 0 && place != 0 {
+                        out.push(' ');
+                    } else {
+                        out.push(
+                            char::from_digit(digits as u32 % 10, 10).unwrap(),
+                        );
+                    }
+                }
+                out.push('\n');
+            }
+        }
+        for y in min.y..=max.y {
+            if self.rulers {
+                write!(out, "{y:>width$} ", width = margin - 1).unwrap();
+            }
+            let mut style = None;
+            for x in min.x..=max.x {
+                let point = Point { x, y };
+                let tile = self.map.get_tile(point).unwrap();
+                let cell = self
+                    .overlays
+                    .iter()
+                    .fold((self.cell)(point, tile), |cell, overlay| {
+                        overlay(point, cell)
+                    });
+                if self.color && style != Some(cell.style()) {
+                    style = Some(cell.style());
+                    out.push_str("\x1B[0");
+                    if cell.bold {
+                        out.push_str(";1");
+                    }
+                    if let Some(fg) = cell.fg {
+                        fg.write_sgr(&mut out, 30);
+                    }
+                    if let Some(bg) = cell.bg {
+                        bg.write_sgr(&mut out, 40);
+                    }
+                    out.push('m');
+                }
+                out.push(cell.glyph);
+            }
+            if self.color && style.is_some() {
+                out.push_str("\x1B[0m");
+            }
+            out.push('\n');
+        }
+        out
+    }
+    pub fn print(&self) {
+        print!("{}", self.render());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Tilemap<u8> {
+        let mut map = Tilemap::new_empty();
+        for y in 0..12 {
+            map.add_row(&(0..12).map(|x| (x + y) % 3).collect::<Vec<_>>());
+        }
+        map
+    }
+
+    #[test]
+    fn plain_with_rulers() {
+        let map = sample();
+        let text = Renderer::new(&map, |_, x| Cell::plain((b'a' + x) as char))
+            .color(false)
+            .rulers(true)
+            .viewport(Point { x: 8, y: 9 }, Point { x: 20, y: 10 })
+            .path([(Point { x: 9, y: 9 }, '*')], Color::RED)
+            .render();
+        assert_eq!(text, "     11\n   8901\n 9 c*bc\n10 abca\n");
+    }
+
+    #[test]
+    fn escapes_only_on_change() {
+        let map = sample();
+        let text = Renderer::new(&map, |_, _| Cell::plain('.'))
+            .color(true)
+            .highlight([Point { x: 1, y: 0 }], Color::Rgb([1, 2, 3]))
+            .viewport(Point { x: 0, y: 0 }, Point { x: 2, y: 0 })
+            .render();
+        assert_eq!(text, "\x1B[0m.\x1B[0;48;2;1;2;3m.\x1B[0m.\x1B[0m\n");
+    }
+}
@@ -0,0 +1,216 @@
+use std::ops::{Index, IndexMut};
+
+use crate::{grid::TiledView, Point, Tilemap};
+
+/// An odd-sized square block of copies of a map, with the original in the
+/// middle. Each copy is a "sector", and sectors are named by their offset
+/// from the middle one: `(0, 0)` is the middle, `(-1, 0)` is just west of
+/// it, `(radius, radius)` is the bottom right corner, and so on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tiling {
+    radius: i32,
+}
+
+impl Tiling {
+    /// Makes a `factor`×`factor` tiling. `factor` must be odd, so that there
+    /// is a middle.
+    pub fn new(factor: i32) -> Tiling {
+        assert!(
+            factor > 0 && factor % 2 == 1,
+            "Tiling factor must be odd and positive, not {factor}"
+        );
+        Tiling { radius: factor / 2 }
+    }
+    pub fn get_factor(&self) -> i32 {
+        self.radius * 2 + 1
+    }
+    /// How many sectors there are between the middle and the edge.
+    pub fn get_radius(&self) -> i32 {
+        self.radius
+    }
+    /// Makes the big map: `factor`×`factor` copies of `map`.
+    pub fn tile<T: Clone>(&self, map: &Tilemap<T>) -> Tilemap<T> {
+        TiledView::new(map).window(
+            Point { x: 0, y: 0 },
+            map.get_width() * self.get_factor(),
+            map.get_height() * self.get_factor(),
+        )
+    }
+    /// Where a point in the original map ends up in the middle sector of
+    /// the big map.
+    pub fn to_middle<T: Clone>(
+        &self,
+        map: &Tilemap<T>,
+        point: Point,
+    ) -> Point {
+        Point {
+            x: point.x + map.get_width() * self.radius,
+            y: point.y + map.get_height() * self.radius,
+        }
+    }
+    /// Which sector a point in the big map is in, given the size of the
+    /// original map.
+    pub fn sector_of(&self, width: i32, height: i32, point: Point) -> Point {
+        Point {
+            x: point.x.div_euclid(width) - self.radius,
+            y: point.y.div_euclid(height) - self.radius,
+        }
+    }
+    /// Every sector, top to bottom, left to right.
+    pub fn sectors(&self) -> impl Iterator<Item = Point> {
+        let radius = self.radius;
+        (-radius..=radius)
+            .flat_map(move |y| (-radius..=radius).map(move |x| Point { x, y }))
+    }
+    /// The four sectors at the edge in line with the middle one. A 1×1
+    /// tiling has no edge, so there are none of these, or of the other
+    /// outer sectors below.
+    pub fn axis_sectors(&self) -> Vec<Point> {
+        let r = self.radius;
+        if r == 0 {
+            return vec![];
+        }
+        vec![
+            Point { x: 0, y: -r },
+            Point { x: -r, y: 0 },
+            Point { x: r, y: 0 },
+            Point { x: 0, y: r },
+        ]
+    }
+    /// The sectors on the edge of the tiling that are neither in line with
+    /// the middle nor in a corner. There are none of these in a 3×3 tiling.
+    pub fn edge_sectors(&self) -> Vec<Point> {
+        let r = self.radius;
+        let mut ret = Vec::with_capacity(8 * (r.max(1) - 1) as usize);
+        for o in (-r + 1..r).filter(|o| *o != 0) {
+            ret.push(Point { x: o, y: -r });
+            ret.push(Point { x: o, y: r });
+            ret.push(Point { x: -r, y: o });
+            ret.push(Point { x: r, y: o });
+        }
+        ret
+    }
+    /// The four corner sectors.
+    pub fn corner_sectors(&self) -> Vec<Point> {
+        let r = self.radius;
+        if r == 0 {
+            return vec![];
+        }
+        vec![
+            Point { x: -r, y: -r },
+            Point { x: r, y: -r },
+            Point { x: -r, y: r },
+            Point { x: r, y: r },
+        ]
+    }
+    /// Adds up something about every tile of the big map, separately for
+    /// each sector. `width` and `height` are the size of the original map.
+    pub fn tally<T: Clone, V: Clone + Default>(
+        &self,
+        big_map: &Tilemap<T>,
+        width: i32,
+        height: i32,
+        mut f: impl FnMut(&mut V, &T),
+    ) -> Sectors<V> {
+        let mut ret = Sectors::new(*self);
+        for (y, row) in big_map.rows().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                let sector = self.sector_of(
+                    width,
+                    height,
+                    Point {
+                        x: x as i32,
+                        y: y as i32,
+                    },
+                );
+                f(&mut ret[sector], tile);
+            }
+        }
+        ret
+    }
+}
+
+/// One value for each sector of a `Tiling`, indexed by sector offset.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Sectors<V> {
+    tiling: Tiling,
+    values: Vec<V>,
+}
+
+impl<V: Clone + Default> Sectors<V> {
+    pub fn new(tiling: Tiling) -> Sectors<V> {
+        let factor = tiling.get_factor() as usize;
+        Sectors {
+            tiling,
+            values: vec![Default::default(); factor * factor],
+        }
+    }
+}
+
+impl<V> Sectors<V> {
+    pub fn get_tiling(&self) -> Tiling {
+        self.tiling
+    }
+    fn index_of(&self, sector: Point) -> Option<usize> {
+        let r = self.tiling.radius;
+        if sector.x < -r || sector.x > r || sector.y < -r || sector.y > r {
+            None
+        } else {
+            Some(
+                ((sector.x + r) + (sector.y + r) * self.tiling.get_factor())
+                    as usize,
+            )
+        }
+    }
+    pub fn get(&self, sector: Point) -> Option<&V> {
+        self.index_of(sector).map(|x| &self.values[x])
+    }
+    pub fn get_mut(&mut self, sector: Point) -> Option<&mut V> {
+        self.index_of(sector).map(|x| &mut self.values[x])
+    }
+    /// Every sector and its value, top to bottom, left to right.
+    pub fn iter(&self) -> impl Iterator<Item = (Point, &V)> {
+        self.tiling.sectors().zip(self.values.iter())
+    }
+}
+
+impl<V> Index<Point> for Sectors<V> {
+    type Output = V;
+    fn index(&self, sector: Point) -> &V {
+        self.get(sector).expect("sector outside the tiling")
+    }
+}
+
+impl<V> IndexMut<Point> for Sectors<V> {
+    fn index_mut(&mut self, sector: Point) -> &mut V {
+        self.get_mut(sector).expect("sector outside the tiling")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn outer_sectors() {
+        let single = Tiling::new(1);
+        assert!(single.axis_sectors().is_empty());
+        assert!(single.edge_sectors().is_empty());
+        assert!(single.corner_sectors().is_empty());
+        assert_eq!(
+            single.sectors().collect::<Vec<_>>(),
+            [Point { x: 0, y: 0 }]
+        );
+        let tiling = Tiling::new(5);
+        assert_eq!(tiling.axis_sectors().len(), 4);
+        assert_eq!(tiling.edge_sectors().len(), 8);
+        assert_eq!(tiling.corner_sectors().len(), 4);
+        // 25 sectors, less the inner 3×3
+        assert_eq!(
+            tiling.axis_sectors().len()
+                + tiling.edge_sectors().len()
+                + tiling.corner_sectors().len(),
+            25 - 9
+        );
+    }
+}
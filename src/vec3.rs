@@ -0,0 +1,326 @@
+use std::{
+    ops::{Add, AddAssign, Div, Index, IndexMut, Mul, Neg, Sub, SubAssign},
+    str::FromStr,
+};
+
+use num::{Float, Num, Signed, ToPrimitive};
+
+/// A point (or direction) in 3-D space. `Point3` and `Point3f` are the ones
+/// the puzzles actually use.
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub struct Vec3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+/// One of the three coordinates of a `Vec3`, for indexing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    pub const ALL: [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+}
+
+impl<T: Neg<Output = T>> Neg for Vec3<T> {
+    type Output = Vec3<T>;
+    fn neg(self) -> Self::Output {
+        Vec3 {
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+        }
+    }
+}
+
+impl<T: Num> Add for Vec3<T> {
+    type Output = Vec3<T>;
+    fn add(self, rhs: Self) -> Self::Output {
+        Vec3 {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+            z: self.z + rhs.z,
+        }
+    }
+}
+
+impl<T: Num> Sub for Vec3<T> {
+    type Output = Vec3<T>;
+    fn sub(self, rhs: Self) -> Self::Output {
+        Vec3 {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+            z: self.z - rhs.z,
+        }
+    }
+}
+
+impl<T: Num + Copy> Mul<T> for Vec3<T> {
+    type Output = Vec3<T>;
+    fn mul(self, rhs: T) -> Self::Output {
+        Vec3 {
+            x: self.x * rhs,
+            y: self.y * rhs,
+            z: self.z * rhs,
+        }
+    }
+}
+
+/// Integer vectors round each coordinate toward zero, same as `/` on the
+/// integers themselves.
+impl<T: Num + Copy> Div<T> for Vec3<T> {
+    type Output = Vec3<T>;
+    fn div(self, rhs: T) -> Self::Output {
+        Vec3 {
+            x: self.x / rhs,
+            y: self.y / rhs,
+            z: self.z / rhs,
+        }
+    }
+}
+
+impl<T: Num + Copy> AddAssign for Vec3<T> {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
+    }
+}
+
+impl<T: Num + Copy> SubAssign for Vec3<T> {
+    fn sub_assign(&mut self, rhs: Self) {
+        *self = *self - rhs;
+    }
+}
+
+impl<T> Index<Axis> for Vec3<T> {
+    type Output = T;
+    fn index(&self, axis: Axis) -> &T {
+        match axis {
+            Axis::X => &self.x,
+            Axis::Y => &self.y,
+            Axis::Z => &self.z,
+        }
+    }
+}
+
+impl<T> IndexMut<Axis> for Vec3<T> {
+    fn index_mut(&mut self, axis: Axis) -> &mut T {
+        match axis {
+            Axis::X => &mut self.x,
+            Axis::Y => &mut self.y,
+            Axis::Z => &mut self.z,
+        }
+    }
+}
+
+impl<T: Num + Copy> Vec3<T> {
+    pub fn zero() -> Vec3<T> {
+        Vec3 {
+            x: T::zero(),
+            y: T::zero(),
+            z: T::zero(),
+        }
+    }
+    /// Builds a vector by asking for each coordinate in turn.
+    pub fn from_fn(mut f: impl FnMut(Axis) -> T) -> Vec3<T> {
+        Vec3 {
+            x: f(Axis::X),
+            y: f(Axis::Y),
+            z: f(Axis::Z),
+        }
+    }
+    /// Applies `f` to each coordinate.
+    pub fn map<U>(self, mut f: impl FnMut(T) -> U) -> Vec3<U> {
+        Vec3 {
+            x: f(self.x),
+            y: f(self.y),
+            z: f(self.z),
+        }
+    }
+    /// Combines the matching coordinates of two vectors with `f`.
+    pub fn zip_with<U>(
+        self,
+        other: Vec3<T>,
+        mut f: impl FnMut(T, T) -> U,
+    ) -> Vec3<U> {
+        Vec3 {
+            x: f(self.x, other.x),
+            y: f(self.y, other.y),
+            z: f(self.z, other.z),
+        }
+    }
+    pub fn dot(self, other: Vec3<T>) -> T {
+        self.x * other.x + self.y * other.y + self.z * other.z
+    }
+    /// The right-handed cross product.
+    pub fn cross(self, other: Vec3<T>) -> Vec3<T> {
+        Vec3 {
+            x: self.y * other.z - self.z * other.y,
+            y: self.z * other.x - self.x * other.z,
+            z: self.x * other.y - self.y * other.x,
+        }
+    }
+    pub fn magnitude_squared(&self) -> T {
+        self.dot(*self)
+    }
+}
+
+impl<T: Num + Copy + PartialOrd> Vec3<T> {
+    /// The smaller of each pair of coordinates. Works on floats, too, but if
+    /// either side is NaN you get the other side.
+    pub fn component_min(self, other: Vec3<T>) -> Vec3<T> {
+        self.zip_with(other, |a, b| if b < a { b } else { a })
+    }
+    pub fn component_max(self, other: Vec3<T>) -> Vec3<T> {
+        self.zip_with(other, |a, b| if b > a { b } else { a })
+    }
+}
+
+impl<T: Signed + Copy + PartialOrd> Vec3<T> {
+    /// Returns this point, but with each coordinate replaced with -1, 0, or 1
+    /// according to its previous sign.
+    pub fn unit(&self) -> Vec3<T> {
+        self.map(|x| x.signum())
+    }
+    pub fn abs(&self) -> Vec3<T> {
+        self.map(|x| x.abs())
+    }
+    /// Taxicab distance from the origin.
+    pub fn manhattan(&self) -> T {
+        let abs = self.abs();
+        abs.x + abs.y + abs.z
+    }
+    /// King's-move distance from the origin: the biggest coordinate.
+    pub fn chebyshev(&self) -> T {
+        let abs = self.abs();
+        let xy = if abs.y > abs.x { abs.y } else { abs.x };
+        if abs.z > xy {
+            abs.z
+        } else {
+            xy
+        }
+    }
+}
+
+impl<T: Num + Copy + ToPrimitive> Vec3<T> {
+    /// Straight-line distance from the origin. Always an `f64`, since
+    /// integer vectors rarely have integer lengths.
+    pub fn euclidean(&self) -> f64 {
+        self.magnitude_squared()
+            .to_f64()
+            .expect("squared magnitude doesn't fit in an f64")
+            .sqrt()
+    }
+}
+
+impl<T: Float> Vec3<T> {
+    /// This vector scaled to length 1. The zero vector comes back as NaNs.
+    pub fn normalized(&self) -> Vec3<T> {
+        let length = self.magnitude_squared().sqrt();
+        self.map(|x| x / length)
+    }
+}
+
+impl<T: FromStr> Vec3<T>
+where
+    T::Err: std::fmt::Debug,
+{
+    /// Parses `x,y,z`. Spaces around the numbers are fine.
+    pub fn from_str_or_panic(s: &str) -> Vec3<T> {
+        let mut split = s.split(',');
+        let x = split.next().unwrap();
+        let y = split.next().unwrap();
+        let z = split.next().unwrap();
+        assert!(split.next().is_none());
+        Vec3 {
+            x: x.trim().parse().unwrap(),
+            y: y.trim().parse().unwrap(),
+            z: z.trim().parse().unwrap(),
+        }
+    }
+}
+
+impl From<Vec3<i32>> for Vec3<f64> {
+    fn from(value: Vec3<i32>) -> Self {
+        Vec3 {
+            x: value.x.into(),
+            y: value.y.into(),
+            z: value.z.into(),
+        }
+    }
+}
+
+/// Rounds to the nearest integer. Panics if a coordinate is NaN or too big
+/// for an `i32`, rather than quietly saturating like `as` would.
+impl From<Vec3<f64>> for Vec3<i32> {
+    fn from(value: Vec3<f64>) -> Self {
+        value.map(|x| {
+            let rounded = x.round();
+            assert!(
+                rounded >= i32::MIN as f64 && rounded <= i32::MAX as f64,
+                "{x} doesn't fit in an i32"
+            );
+            rounded as i32
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn products() {
+        let x = Vec3 { x: 1, y: 0, z: 0 };
+        let y = Vec3 { x: 0, y: 1, z: 0 };
+        assert_eq!(x.cross(y), Vec3 { x: 0, y: 0, z: 1 });
+        assert_eq!(y.cross(x), Vec3 { x: 0, y: 0, z: -1 });
+        let v = Vec3 { x: 2, y: -3, z: 4 };
+        assert_eq!(v.dot(v.cross(x)), 0);
+        assert_eq!(v.dot(Vec3 { x: 1, y: 1, z: 1 }), 3);
+    }
+
+    #[test]
+    fn norms() {
+        let v = Vec3 { x: 2, y: -6, z: 3 };
+        assert_eq!(v.manhattan(), 11);
+        assert_eq!(v.chebyshev(), 6);
+        assert_eq!(v.magnitude_squared(), 49);
+        assert_eq!(v.euclidean(), 7.0);
+        assert_eq!(v.abs(), Vec3 { x: 2, y: 6, z: 3 });
+        assert_eq!(v.unit(), Vec3 { x: 1, y: -1, z: 1 });
+    }
+
+    #[test]
+    fn components() {
+        let a = Vec3 { x: 1, y: 5, z: -2 };
+        let b = Vec3 { x: 3, y: 0, z: -2 };
+        assert_eq!(a.component_min(b), Vec3 { x: 1, y: 0, z: -2 });
+        assert_eq!(a.component_max(b), Vec3 { x: 3, y: 5, z: -2 });
+        let mut c = a;
+        c += b;
+        c[Axis::Y] -= 1;
+        assert_eq!(c, Vec3 { x: 4, y: 4, z: -4 });
+        assert_eq!(c / 4, Vec3 { x: 1, y: 1, z: -1 });
+    }
+
+    #[test]
+    fn conversions() {
+        let float: Vec3<f64> = Vec3 { x: 1, y: -2, z: 3 }.into();
+        assert_eq!(
+            float,
+            Vec3 {
+                x: 1.0,
+                y: -2.0,
+                z: 3.0
+            }
+        );
+        let back: Vec3<i32> = (float * 1.4).into();
+        assert_eq!(back, Vec3 { x: 1, y: -3, z: 4 });
+    }
+}